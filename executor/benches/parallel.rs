@@ -0,0 +1,117 @@
+//! Sequential against parallel execution of a block of transfers.
+use std::{hint::black_box, num::NonZeroUsize, sync::Arc};
+
+use criterion::{BatchSize, BenchmarkId, Criterion, criterion_group, criterion_main};
+use executor::{
+    BlockEnv, PintBlockExecutor, database::State, spec::ChainSpec, traits::BlockExecutor,
+    transaction::ExecutableTranasction,
+};
+use primitives::{
+    signature::Signature,
+    types::{Address, B256, U256},
+};
+use storage::{
+    PintStateProviderFactory,
+    db::InMemoryDB,
+    genesis::{Genesis, GenesisAccount, init_genesis},
+    traits::{StateProviderBox, StateProviderFactory},
+};
+use transaction::{PintTx, signed::Signed, transaction::TxEnvelope};
+
+const TRANSACTIONS: usize = 2_000;
+
+fn setup(senders: usize) -> (InMemoryDB, Genesis, Vec<Address>) {
+    let addresses: Vec<_> = (0..senders * 2).map(|_| Address::random()).collect();
+    let account = GenesisAccount {
+        balance: U256::from(1_000_000),
+        ..Default::default()
+    };
+    let genesis = addresses
+        .iter()
+        .fold(Genesis::default(), |genesis, address| {
+            genesis.with_account(address.clone(), account.clone())
+        });
+    let db = InMemoryDB::new();
+    init_genesis(&db, &genesis).unwrap();
+    (db, genesis, addresses)
+}
+
+// Transfers from the first half of the accounts to the second half, so there are `senders`
+// independent groups
+fn transfers(addresses: &[Address]) -> Vec<ExecutableTranasction> {
+    let senders = addresses.len() / 2;
+    (0..TRANSACTIONS)
+        .map(|index| {
+            let sender = index % senders;
+            let tx = PintTx {
+                chain_id: 0,
+                nonce: (index / senders) as u64,
+                to: addresses[senders + sender].clone(),
+                fee: 1,
+                value: U256::from(1),
+            };
+            let hash = B256::from(U256::from(index));
+            let signature = Signature::from_bytes_and_parity(&[0; 64], false);
+            ExecutableTranasction {
+                tx_type: 0,
+                hash,
+                chain_id: 0,
+                sender: addresses[sender].clone(),
+                receiver: tx.to.clone(),
+                nonce: tx.nonce,
+                value: tx.value,
+                fee: U256::from(tx.fee),
+                transaction: TxEnvelope::Pint(Signed::new(tx, signature, hash)),
+            }
+        })
+        .collect()
+}
+
+fn executor(db: &InMemoryDB, genesis: &Genesis) -> PintBlockExecutor<StateProviderBox> {
+    let state = State::new(PintStateProviderFactory::new(db.clone()).latest().unwrap());
+    let env = BlockEnv::new(&genesis.header(), 1, Address::default());
+    let mut executor = PintBlockExecutor::new(state, Arc::new(ChainSpec::default()), env);
+    executor.prepare_execute().unwrap();
+    executor
+}
+
+fn bench_execution(c: &mut Criterion) {
+    let mut group = c.benchmark_group("execute_block");
+    for senders in [1, 16, 256] {
+        let (db, genesis, addresses) = setup(senders);
+        let txs = transfers(&addresses);
+
+        group.bench_with_input(BenchmarkId::new("sequential", senders), &txs, |b, txs| {
+            b.iter_batched(
+                || executor(&db, &genesis),
+                |mut executor| {
+                    for tx in txs {
+                        executor.execute_transaction(tx).unwrap();
+                    }
+                    black_box(executor)
+                },
+                BatchSize::LargeInput,
+            )
+        });
+        for threads in [2, 4, 8] {
+            let id = BenchmarkId::new(format!("parallel_{threads}"), senders);
+            let threads = NonZeroUsize::new(threads).unwrap();
+            group.bench_with_input(id, &txs, |b, txs| {
+                b.iter_batched(
+                    || executor(&db, &genesis),
+                    |mut executor| {
+                        executor
+                            .execute_transactions_parallel(txs, threads)
+                            .unwrap();
+                        black_box(executor)
+                    },
+                    BatchSize::LargeInput,
+                )
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_execution);
+criterion_main!(benches);
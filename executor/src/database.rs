@@ -2,23 +2,32 @@
 //!
 use std::collections::HashMap;
 
-use primitives::{account::Account, types::Address};
+use primitives::{
+    account::{Account, AccountStorage},
+    block::{body::BlockBody, header::Header},
+    proofs::calculate_state_root,
+    types::{Address, B256, BlockHash, StorageKey, StorageValue, TxHash, U256},
+};
 use storage::{
-    db::Database,
+    db::{AccountChangeset, AccountsPage, Database, StorageChangeset, TransactionMeta},
     error::{DatabaseError, ProviderError},
     traits::{AccountReader, StateProvider},
 };
 
+use transaction::TransactionSigned;
+
 use crate::{
     Receipt,
-    error::{BlockExecutionError, StateError},
+    error::{BlockExecutionError, BlockValidationError, StateError},
+    inspector::{Inspector, NoopInspector},
+    journal::{Checkpoint, JournaledState},
     transaction::ExecutableTranasction,
 };
 
 /// In Memory State that represent that block-number
 pub struct State<DB> {
     pub database: DB,
-    pub transition_state: Option<HashMap<Address, Account>>,
+    pub transition_state: Option<JournaledState>,
 }
 
 impl<DB: StateProvider> State<DB> {
@@ -32,63 +41,175 @@ impl<DB: StateProvider> State<DB> {
     pub fn prepare_execute(&mut self) -> Result<(), StateError> {
         let res = self.database.prepare_execute();
         self.transition_state = match res {
-            Ok(state) => Some(state),
+            Ok(state) => Some(JournaledState::new(state)),
             Err(_) => return Err(StateError::PreareExecutionError),
         };
 
         Ok(())
     }
 
+    /// Applies the transfer of the transaction atomically.
+    /// An invalid transaction is rejected with a [BlockValidationError] and leaves the state
+    /// untouched.
     pub fn execute_transaction(
         &mut self,
         tx: &ExecutableTranasction,
     ) -> Result<Receipt, BlockExecutionError> {
-        if self.transition_state.is_none() {
-            return Err(BlockExecutionError::StateNotPrepared);
-        }
+        self.inspect_transaction(tx, &mut NoopInspector)
+    }
 
-        let ExecutableTranasction {
-            tx_type,
-            chain_id: _,
-            sender,
-            receiver,
-            nonce: _,
-            value,
-        } = tx;
-
-        let state = self.transition_state.as_mut().unwrap();
-
-        // update receiver's info
-        match state.get_mut(&receiver) {
-            Some(account) => {
-                account.balance += value;
-            }
-            None => {
-                let mut account = Account::default();
-                account.balance += value;
-                state.insert(receiver.clone(), account);
-            }
+    /// Like [State::execute_transaction], reporting every step of the transaction to the
+    /// inspector. The steps of a rejected transaction are reported before it is reverted.
+    pub fn inspect_transaction<I: Inspector>(
+        &mut self,
+        tx: &ExecutableTranasction,
+        inspector: &mut I,
+    ) -> Result<Receipt, BlockExecutionError> {
+        let state = self.journaled_state()?;
+        inspector.transaction_start(tx);
+        let checkpoint = state.checkpoint();
+        let result = transact(state, tx, inspector);
+        match result {
+            Ok(_) => state.commit(checkpoint),
+            Err(_) => state.revert_to(checkpoint),
         }
+        inspector.transaction_end(tx, &result);
+        result
+    }
 
-        // update sender's info
-        match state.get_mut(&sender) {
-            Some(account) => {
-                account.nonce += 1;
-                account.balance -= value;
-            }
-            None => {
-                // It should never happen!
-                return Err(BlockExecutionError::SenderNotFound);
-            }
-        };
+    /// Credits the account, which is created if it doesn't exist
+    pub fn increment_balance(
+        &mut self,
+        address: &Address,
+        amount: U256,
+    ) -> Result<(), BlockExecutionError> {
+        let state = self.journaled_state()?;
+        let mut account = state.get(address).copied().unwrap_or_default();
+        account.balance = account
+            .balance
+            .checked_add(amount)
+            .ok_or_else(|| BlockValidationError::BalanceOverflow(address.clone()))?;
+        state.set(address.clone(), account);
+        Ok(())
+    }
+
+    /// Overwrites the account
+    pub fn set_account(
+        &mut self,
+        address: Address,
+        account: Account,
+    ) -> Result<(), BlockExecutionError> {
+        self.journaled_state()?.set(address, account);
+        Ok(())
+    }
+
+    pub fn checkpoint(&mut self) -> Result<Checkpoint, BlockExecutionError> {
+        Ok(self.journaled_state()?.checkpoint())
+    }
+
+    pub fn commit(&mut self, checkpoint: Checkpoint) -> Result<(), BlockExecutionError> {
+        self.journaled_state()?.commit(checkpoint);
+        Ok(())
+    }
+
+    pub fn revert_to(&mut self, checkpoint: Checkpoint) -> Result<(), BlockExecutionError> {
+        self.journaled_state()?.revert_to(checkpoint);
+        Ok(())
+    }
+
+    /// Post-state of the accounts changed since [State::prepare_execute]
+    pub fn diff(&self) -> Result<HashMap<Address, Account>, BlockExecutionError> {
+        let state = self
+            .transition_state
+            .as_ref()
+            .ok_or(BlockExecutionError::StateNotPrepared)?;
+        Ok(state
+            .touched()
+            .iter()
+            .filter_map(|address| Some((address.clone(), *state.get(address)?)))
+            .collect())
+    }
 
-        Ok(Receipt {
-            tx_type: tx_type.clone(),
-            success: true,
-        })
+    fn journaled_state(&mut self) -> Result<&mut JournaledState, BlockExecutionError> {
+        self.transition_state
+            .as_mut()
+            .ok_or(BlockExecutionError::StateNotPrepared)
     }
 }
 
+// Checks and applies the transfer step by step. The caller reverts the writes if it fails.
+pub(crate) fn transact<I: Inspector>(
+    state: &mut JournaledState,
+    tx: &ExecutableTranasction,
+    inspector: &mut I,
+) -> Result<Receipt, BlockExecutionError> {
+    let ExecutableTranasction {
+        tx_type,
+        hash,
+        chain_id: _,
+        sender,
+        receiver,
+        nonce,
+        value,
+        fee,
+        transaction: _,
+    } = tx;
+
+    let mut sender_account = state.get(sender).copied().unwrap_or_default();
+    if *nonce != sender_account.nonce {
+        return Err(BlockValidationError::NonceMismatch {
+            expected: sender_account.nonce,
+            got: *nonce,
+        }
+        .into());
+    }
+    let next_nonce = sender_account
+        .nonce
+        .checked_add(1)
+        .ok_or(BlockValidationError::NonceOverflow)?;
+    let cost = value
+        .checked_add(*fee)
+        .ok_or(BlockValidationError::CostOverflow)?;
+    if sender_account.balance < cost {
+        return Err(BlockValidationError::InsufficientBalance {
+            balance: sender_account.balance,
+            cost,
+        }
+        .into());
+    }
+    inspector.nonce_bump(sender, sender_account.nonce, next_nonce);
+    inspector.fee_transfer(sender, *fee);
+    inspector.balance_change(
+        sender,
+        sender_account.balance,
+        sender_account.balance - cost,
+    );
+    sender_account.nonce = next_nonce;
+    sender_account.balance -= cost;
+    state.set(sender.clone(), sender_account);
+
+    // a transfer to itself only pays the fee
+    let mut receiver_account = state.get(receiver).copied().unwrap_or_default();
+    let balance = receiver_account
+        .balance
+        .checked_add(*value)
+        .ok_or_else(|| BlockValidationError::BalanceOverflow(receiver.clone()))?;
+    inspector.balance_change(receiver, receiver_account.balance, balance);
+    receiver_account.balance = balance;
+    state.set(receiver.clone(), receiver_account);
+
+    // cumulative_fee is filled by the block executor
+    Ok(Receipt {
+        tx_type: *tx_type,
+        tx_hash: *hash,
+        success: true,
+        fee: *fee,
+        cumulative_fee: *fee,
+        sender_nonce: state.get(sender).map_or(0, |account| account.nonce),
+        receiver_nonce: receiver_account.nonce,
+    })
+}
+
 /// StateProvider
 #[derive(Default)]
 pub struct StateProviderDatabase<DB>(pub DB);
@@ -119,9 +240,29 @@ impl<DB: Database> AccountReader for StateProviderDatabase<DB> {
 
         Ok(res)
     }
+
+    fn accounts_page(
+        &self,
+        start: &Address,
+        limit: usize,
+    ) -> storage::traits::ProviderResult<AccountsPage> {
+        Ok(self.inner().accounts_range(start, limit)?)
+    }
+
+    fn total_accounts(&self) -> storage::traits::ProviderResult<u64> {
+        Ok(self.inner().account_count()?)
+    }
 }
 
 impl<DB: Database> StateProvider for StateProviderDatabase<DB> {
+    fn account_storage(
+        &self,
+        addr: &Address,
+        key: &StorageKey,
+    ) -> storage::traits::ProviderResult<Option<StorageValue>> {
+        Ok(self.inner().storage(addr, key)?)
+    }
+
     fn account_balance(
         &self,
         addr: &Address,
@@ -136,7 +277,22 @@ impl<DB: Database> StateProvider for StateProviderDatabase<DB> {
     }
 
     fn prepare_execute(&mut self) -> storage::traits::ProviderResult<HashMap<Address, Account>> {
-        todo!()
+        let number = self.inner().block_number();
+        Ok(self.inner().copy_state_from_block_no(number)?)
+    }
+
+    fn state_root(
+        &self,
+        diff: &HashMap<Address, Account>,
+    ) -> storage::traits::ProviderResult<B256> {
+        let number = self.inner().block_number();
+        let mut state = self.inner().copy_state_from_block_no(number)?;
+        state.extend(
+            diff.iter()
+                .map(|(address, account)| (address.clone(), *account)),
+        );
+        let storage = self.inner().copy_storage_from_block_no(number)?;
+        Ok(calculate_state_root(&state, &storage))
     }
 }
 
@@ -145,14 +301,22 @@ impl<DB: Database> Database for StateProviderDatabase<DB> {
         &self,
         address: &primitives::types::Address,
     ) -> Result<Option<primitives::account::Account>, DatabaseError> {
-        todo!()
+        self.inner().basic(address)
+    }
+
+    fn basic_at_block(
+        &self,
+        address: &Address,
+        number: u64,
+    ) -> Result<Option<Account>, DatabaseError> {
+        self.inner().basic_at_block(address, number)
     }
 
     fn block_hash(
         &self,
         number: u64,
     ) -> Result<Option<primitives::types::BlockHash>, DatabaseError> {
-        todo!()
+        self.inner().block_hash(number)
     }
 
     fn block_number(&self) -> u64 {
@@ -165,4 +329,81 @@ impl<DB: Database> Database for StateProviderDatabase<DB> {
     ) -> Result<HashMap<Address, Account>, DatabaseError> {
         self.inner().copy_state_from_block_no(number)
     }
+
+    fn header(&self, number: u64) -> Result<Option<Header>, DatabaseError> {
+        self.inner().header(number)
+    }
+
+    fn body(&self, number: u64) -> Result<Option<BlockBody<TransactionSigned>>, DatabaseError> {
+        self.inner().body(number)
+    }
+
+    fn block_number_by_hash(&self, hash: &BlockHash) -> Result<Option<u64>, DatabaseError> {
+        self.inner().block_number_by_hash(hash)
+    }
+
+    fn transaction_meta(&self, hash: &TxHash) -> Result<Option<TransactionMeta>, DatabaseError> {
+        self.inner().transaction_meta(hash)
+    }
+
+    fn receipts(&self, number: u64) -> Result<Option<Vec<Receipt>>, DatabaseError> {
+        self.inner().receipts(number)
+    }
+
+    fn earliest_state_block(&self) -> Result<u64, DatabaseError> {
+        self.inner().earliest_state_block()
+    }
+
+    fn accounts_range(&self, start: &Address, limit: usize) -> Result<AccountsPage, DatabaseError> {
+        self.inner().accounts_range(start, limit)
+    }
+
+    fn account_count(&self) -> Result<u64, DatabaseError> {
+        self.inner().account_count()
+    }
+
+    fn accounts_range_at_block(
+        &self,
+        number: u64,
+        start: &Address,
+        limit: usize,
+    ) -> Result<AccountsPage, DatabaseError> {
+        self.inner().accounts_range_at_block(number, start, limit)
+    }
+
+    fn account_count_at_block(&self, number: u64) -> Result<u64, DatabaseError> {
+        self.inner().account_count_at_block(number)
+    }
+
+    fn storage(
+        &self,
+        address: &Address,
+        key: &StorageKey,
+    ) -> Result<Option<StorageValue>, DatabaseError> {
+        self.inner().storage(address, key)
+    }
+
+    fn storage_at_block(
+        &self,
+        address: &Address,
+        key: &StorageKey,
+        number: u64,
+    ) -> Result<Option<StorageValue>, DatabaseError> {
+        self.inner().storage_at_block(address, key, number)
+    }
+
+    fn copy_storage_from_block_no(
+        &self,
+        number: u64,
+    ) -> Result<HashMap<Address, AccountStorage>, DatabaseError> {
+        self.inner().copy_storage_from_block_no(number)
+    }
+
+    fn account_changeset(&self, number: u64) -> Result<Option<AccountChangeset>, DatabaseError> {
+        self.inner().account_changeset(number)
+    }
+
+    fn storage_changeset(&self, number: u64) -> Result<Option<StorageChangeset>, DatabaseError> {
+        self.inner().storage_changeset(number)
+    }
 }
@@ -1,3 +1,6 @@
+use primitives::types::{Address, B256, BlockHash, ChainId, TxHash, U256};
+use storage::error::ProviderError;
+
 #[derive(Debug)]
 pub enum BlockExecutionError {
     Validation(BlockValidationError),
@@ -5,11 +8,90 @@ pub enum BlockExecutionError {
     StateNotPrepared,
     SenderNotFound,
     InvalidTx,
+    Provider(ProviderError),
+    // canonical head the block should extend is missing from the database
+    ParentNotFound(u64),
+    // transaction to trace is not in the canonical chain
+    TransactionNotFound(TxHash),
+}
+
+impl From<ProviderError> for BlockExecutionError {
+    fn from(value: ProviderError) -> Self {
+        Self::Provider(value)
+    }
+}
+
+impl From<BlockValidationError> for BlockExecutionError {
+    fn from(value: BlockValidationError) -> Self {
+        Self::Validation(value)
+    }
 }
 
 #[derive(Debug)]
 pub enum BlockValidationError {
-    InvalidTx,
+    // transaction was signed for another chain
+    ChainIdMismatch {
+        expected: ChainId,
+        got: ChainId,
+    },
+    // transaction nonce is not the next nonce of the sender
+    NonceMismatch {
+        expected: u64,
+        got: u64,
+    },
+    // sender nonce is at u64::MAX and cannot be bumped
+    NonceOverflow,
+    // value plus fee does not fit into U256
+    CostOverflow,
+    // sender cannot pay value plus fee
+    InsufficientBalance {
+        balance: U256,
+        cost: U256,
+    },
+    // receiver balance would not fit into U256
+    BalanceOverflow(Address),
+    // block has more transactions than the chain spec allows
+    TooManyTransactions {
+        limit: usize,
+        got: usize,
+    },
+    // block is not the child of the canonical head
+    BlockNumberMismatch {
+        expected: u64,
+        got: u64,
+    },
+    // header does not point to the hash of its parent
+    ParentHashMismatch {
+        expected: BlockHash,
+        got: BlockHash,
+    },
+    // block is not newer than its parent
+    TimestampNotAfterParent {
+        parent: u64,
+        got: u64,
+    },
+    // signer of the transaction cannot be recovered from its signature
+    SenderRecovery(TxHash),
+    // header does not commit to the transactions of the body
+    TransactionRootMismatch {
+        expected: B256,
+        computed: B256,
+    },
+    // header does not commit to the receipts of the re-executed transactions
+    ReceiptsRootMismatch {
+        expected: B256,
+        computed: B256,
+    },
+    // header does not commit to the state after re-executing the block
+    StateRootMismatch {
+        expected: B256,
+        computed: B256,
+    },
+    // re-executing the block produced another header
+    BlockHashMismatch {
+        expected: BlockHash,
+        computed: BlockHash,
+    },
 }
 
 pub enum StateError {
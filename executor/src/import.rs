@@ -0,0 +1,214 @@
+//! Import of blocks built by other nodes.
+//! A received block is checked against the canonical head, re-executed on the state of its
+//! parent and only committed if the execution reproduces its header.
+use std::sync::Arc;
+
+use ::transaction::TransactionSigned;
+use primitives::{
+    block::{Block, body::SealedBlock, header::SealedHeader},
+    types::BlockHash,
+};
+use storage::{
+    PintStateProviderFactory, db::DatabaseMut, error::ProviderError, traits::StateProviderFactory,
+};
+
+use crate::{
+    PintBlockExecutor,
+    database::State,
+    error::{BlockExecutionError, BlockValidationError},
+    spec::ChainSpec,
+};
+
+/// Appends received blocks to the canonical chain of the database
+pub struct BlockImporter<DB> {
+    db: DB,
+    spec: Arc<ChainSpec>,
+}
+
+impl<DB> BlockImporter<DB>
+where
+    DB: DatabaseMut + Clone + Send + Sync + 'static,
+{
+    pub fn new(db: DB, spec: Arc<ChainSpec>) -> Self {
+        Self { db, spec }
+    }
+
+    /// Checks that the header extends the canonical head
+    pub fn validate_header(&self, header: &SealedHeader) -> Result<(), BlockExecutionError> {
+        let head = self.db.block_number();
+        let parent = self
+            .db
+            .header(head)
+            .map_err(ProviderError::from)?
+            .ok_or(BlockExecutionError::ParentNotFound(head))?;
+        let parent_hash = self
+            .db
+            .block_hash(head)
+            .map_err(ProviderError::from)?
+            .ok_or(BlockExecutionError::ParentNotFound(head))?;
+
+        if header.number() != head + 1 {
+            return Err(BlockValidationError::BlockNumberMismatch {
+                expected: head + 1,
+                got: header.number(),
+            }
+            .into());
+        }
+        let header = header.header();
+        if header.previous_hash != parent_hash {
+            return Err(BlockValidationError::ParentHashMismatch {
+                expected: parent_hash,
+                got: header.previous_hash,
+            }
+            .into());
+        }
+        if header.timestamp <= parent.timestamp {
+            return Err(BlockValidationError::TimestampNotAfterParent {
+                parent: parent.timestamp,
+                got: header.timestamp,
+            }
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Verifies the block by re-executing it and commits it on top of the canonical head.
+    /// Nothing is written if any check fails.
+    pub fn import_block(
+        &self,
+        block: &SealedBlock<Block<TransactionSigned>>,
+    ) -> Result<BlockHash, BlockExecutionError> {
+        self.validate_header(block.sealed_header())?;
+
+        let provider = PintStateProviderFactory::new(self.db.clone());
+        let state = State::new(provider.latest()?);
+        let outcome = PintBlockExecutor::execute_block(state, self.spec.clone(), block)?;
+
+        let hash = outcome.block.sealed_header().hash();
+        self.db
+            .commit_block(outcome.into())
+            .map_err(ProviderError::from)?;
+        Ok(hash)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use primitives::{
+        block::{body::BlockBody, header::Header},
+        types::{Address, B256},
+    };
+    use storage::{
+        db::{Database, InMemoryDB},
+        genesis::init_genesis,
+    };
+
+    use crate::tests::{build_block, genesis, test_spec};
+
+    use super::*;
+
+    fn reseal(
+        block: &SealedBlock<Block<TransactionSigned>>,
+        modify: impl FnOnce(&mut Header),
+    ) -> SealedBlock<Block<TransactionSigned>> {
+        let mut header = block.sealed_header().header().clone();
+        modify(&mut header);
+        SealedBlock::new(SealedHeader::seal_slow(header), block.body().clone())
+    }
+
+    fn assert_rejected(
+        importer: &BlockImporter<InMemoryDB>,
+        block: &SealedBlock<Block<TransactionSigned>>,
+        expected: impl Fn(&BlockValidationError) -> bool,
+    ) {
+        match importer.import_block(block) {
+            Err(BlockExecutionError::Validation(err)) => assert!(expected(&err), "{err:?}"),
+            other => panic!("unexpected import result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_import_block() {
+        let builder = InMemoryDB::new();
+        init_genesis(&builder, &genesis()).unwrap();
+        let outcome = build_block(&builder, test_spec());
+        let payout = outcome.fees + outcome.reward;
+        let block = outcome.block;
+
+        let db = InMemoryDB::new();
+        init_genesis(&db, &genesis()).unwrap();
+        let importer = BlockImporter::new(db.clone(), test_spec());
+        let hash = importer.import_block(&block).unwrap();
+
+        assert_eq!(hash, block.sealed_header().hash());
+        assert_eq!(db.block_number(), 1);
+        assert_eq!(db.block_hash(1).unwrap(), Some(hash));
+        assert_eq!(db.receipts(1).unwrap().unwrap().len(), 2);
+        // the beneficiary named in the header is paid
+        let beneficiary = &block.sealed_header().header().beneficiary;
+        assert_eq!(db.basic(beneficiary).unwrap().unwrap().balance, payout);
+
+        // the block is not a child of the new head anymore
+        assert_rejected(&importer, &block, |err| {
+            matches!(
+                err,
+                BlockValidationError::BlockNumberMismatch {
+                    expected: 2,
+                    got: 1
+                }
+            )
+        });
+    }
+
+    #[test]
+    fn test_invalid_blocks_are_not_committed() {
+        let builder = InMemoryDB::new();
+        init_genesis(&builder, &genesis()).unwrap();
+        let block = build_block(&builder, test_spec()).block;
+
+        let db = InMemoryDB::new();
+        init_genesis(&db, &genesis()).unwrap();
+        let importer = BlockImporter::new(db.clone(), test_spec());
+
+        let orphan = reseal(&block, |header| header.previous_hash = B256::ZERO);
+        assert_rejected(
+            &importer,
+            &orphan,
+            |err| matches!(err, BlockValidationError::ParentHashMismatch { got, .. } if got.is_zero()),
+        );
+
+        let stale = reseal(&block, |header| header.timestamp = 0);
+        assert_rejected(&importer, &stale, |err| {
+            matches!(
+                err,
+                BlockValidationError::TimestampNotAfterParent { parent: 0, got: 0 }
+            )
+        });
+
+        let (header, body) = block.clone().split();
+        let truncated = SealedBlock::new(
+            header,
+            BlockBody {
+                transaction: body.transaction[..1].to_vec(),
+            },
+        );
+        assert_rejected(&importer, &truncated, |err| {
+            matches!(err, BlockValidationError::TransactionRootMismatch { .. })
+        });
+
+        let wrong_receipts = reseal(&block, |header| header.receipts_root = B256::ZERO);
+        assert_rejected(&importer, &wrong_receipts, |err| {
+            matches!(err, BlockValidationError::ReceiptsRootMismatch { .. })
+        });
+
+        // the fees are credited to the beneficiary the header names, and the state root
+        // committed to another one
+        let other = reseal(&block, |header| header.beneficiary = Address::random());
+        assert_rejected(&importer, &other, |err| {
+            matches!(err, BlockValidationError::StateRootMismatch { .. })
+        });
+
+        assert_eq!(db.block_number(), 0);
+        importer.import_block(&block).unwrap();
+    }
+}
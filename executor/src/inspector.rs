@@ -0,0 +1,383 @@
+//! Hooks into transaction execution.
+//! An [Inspector] is told about every step [crate::database::State] takes while executing a
+//! transaction, which is what the built-in tracers and [trace_transaction] are made of.
+use std::{collections::BTreeMap, sync::Arc};
+
+use ::transaction::{TransactionSigned, traits::SignedTransaction};
+use primitives::{
+    block::{Block, header::Header},
+    types::{Address, TxHash, U256},
+};
+use serde_json::{Value, json};
+use storage::traits::{BlockReader, StateProviderFactory, TransactionsProvider};
+
+use crate::{
+    BlockEnv, PintBlockExecutor, Receipt,
+    database::State,
+    error::{BlockExecutionError, BlockValidationError},
+    spec::ChainSpec,
+    transaction::ExecutableTranasction,
+};
+
+/// Observer of transaction execution. Every hook does nothing by default.
+/// Steps are reported as they happen, so a rejected transaction may report changes that are
+/// reverted afterwards. [Inspector::transaction_end] tells whether they were kept.
+pub trait Inspector {
+    fn transaction_start(&mut self, _tx: &ExecutableTranasction) {}
+
+    fn transaction_end(
+        &mut self,
+        _tx: &ExecutableTranasction,
+        _result: &Result<Receipt, BlockExecutionError>,
+    ) {
+    }
+
+    fn balance_change(&mut self, _address: &Address, _old: U256, _new: U256) {}
+
+    fn nonce_bump(&mut self, _address: &Address, _old: u64, _new: u64) {}
+
+    // The fee leaves the sender with the transaction and is paid to the beneficiary when the
+    // block is finished
+    fn fee_transfer(&mut self, _from: &Address, _amount: U256) {}
+}
+
+/// Inspector of plain execution
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoopInspector;
+
+impl Inspector for NoopInspector {}
+
+fn hex_address(address: &Address) -> String {
+    format!("0x{}", address.get_addr_hex())
+}
+
+/// Records every transaction with its steps as JSON
+#[derive(Debug, Clone, Default)]
+pub struct CallTracer {
+    traces: Vec<Value>,
+    steps: Vec<Value>,
+}
+
+impl CallTracer {
+    /// One object per executed transaction, in execution order
+    pub fn traces(&self) -> &[Value] {
+        &self.traces
+    }
+
+    pub fn into_json(self) -> Value {
+        Value::Array(self.traces)
+    }
+}
+
+impl Inspector for CallTracer {
+    fn transaction_start(&mut self, _tx: &ExecutableTranasction) {
+        self.steps.clear();
+    }
+
+    fn transaction_end(
+        &mut self,
+        tx: &ExecutableTranasction,
+        result: &Result<Receipt, BlockExecutionError>,
+    ) {
+        let mut trace = json!({
+            "hash": tx.hash.to_string(),
+            "from": hex_address(&tx.sender),
+            "to": hex_address(&tx.receiver),
+            "nonce": tx.nonce,
+            "value": tx.value.to_string(),
+            "fee": tx.fee.to_string(),
+            "success": result.is_ok(),
+            "steps": std::mem::take(&mut self.steps),
+        });
+        if let Err(err) = result {
+            trace["error"] = Value::String(format!("{err:?}"));
+        }
+        self.traces.push(trace);
+    }
+
+    fn balance_change(&mut self, address: &Address, old: U256, new: U256) {
+        self.steps.push(json!({
+            "op": "balance",
+            "address": hex_address(address),
+            "from": old.to_string(),
+            "to": new.to_string(),
+        }));
+    }
+
+    fn nonce_bump(&mut self, address: &Address, old: u64, new: u64) {
+        self.steps.push(json!({
+            "op": "nonce",
+            "address": hex_address(address),
+            "from": old,
+            "to": new,
+        }));
+    }
+
+    fn fee_transfer(&mut self, from: &Address, amount: U256) {
+        self.steps.push(json!({
+            "op": "fee",
+            "address": hex_address(from),
+            "amount": amount.to_string(),
+        }));
+    }
+}
+
+/// Value of an account field before and after the traced transactions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Change<T> {
+    pub from: T,
+    pub to: T,
+}
+
+impl<T> Change<T> {
+    // Keeps the first value and takes the latest one
+    fn merge(self, later: Self) -> Self {
+        Self {
+            from: self.from,
+            to: later.to,
+        }
+    }
+}
+
+/// Changed fields of an account
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AccountDiff {
+    pub balance: Option<Change<U256>>,
+    pub nonce: Option<Change<u64>>,
+}
+
+/// Collects the account changes of the successful transactions
+#[derive(Debug, Clone, Default)]
+pub struct StateDiffTracer {
+    diff: BTreeMap<Address, AccountDiff>,
+    // changes of the running transaction, kept only if it succeeds
+    pending: Vec<(Address, AccountDiff)>,
+}
+
+impl StateDiffTracer {
+    pub fn diff(&self) -> &BTreeMap<Address, AccountDiff> {
+        &self.diff
+    }
+
+    pub fn into_json(self) -> Value {
+        let accounts = self
+            .diff
+            .into_iter()
+            .map(|(address, account)| {
+                let mut fields = serde_json::Map::new();
+                if let Some(balance) = account.balance {
+                    fields.insert(
+                        "balance".to_string(),
+                        json!({ "from": balance.from.to_string(), "to": balance.to.to_string() }),
+                    );
+                }
+                if let Some(nonce) = account.nonce {
+                    fields.insert(
+                        "nonce".to_string(),
+                        json!({ "from": nonce.from, "to": nonce.to }),
+                    );
+                }
+                (hex_address(&address), Value::Object(fields))
+            })
+            .collect();
+        Value::Object(accounts)
+    }
+}
+
+impl Inspector for StateDiffTracer {
+    fn transaction_start(&mut self, _tx: &ExecutableTranasction) {
+        self.pending.clear();
+    }
+
+    fn transaction_end(
+        &mut self,
+        _tx: &ExecutableTranasction,
+        result: &Result<Receipt, BlockExecutionError>,
+    ) {
+        if result.is_err() {
+            self.pending.clear();
+            return;
+        }
+        for (address, change) in self.pending.drain(..) {
+            let account = self.diff.entry(address).or_default();
+            if let Some(balance) = change.balance {
+                account.balance = Some(account.balance.map_or(balance, |old| old.merge(balance)));
+            }
+            if let Some(nonce) = change.nonce {
+                account.nonce = Some(account.nonce.map_or(nonce, |old| old.merge(nonce)));
+            }
+        }
+    }
+
+    fn balance_change(&mut self, address: &Address, old: U256, new: U256) {
+        let change = AccountDiff {
+            balance: Some(Change { from: old, to: new }),
+            ..Default::default()
+        };
+        self.pending.push((address.clone(), change));
+    }
+
+    fn nonce_bump(&mut self, address: &Address, old: u64, new: u64) {
+        let change = AccountDiff {
+            nonce: Some(Change { from: old, to: new }),
+            ..Default::default()
+        };
+        self.pending.push((address.clone(), change));
+    }
+}
+
+/// Re-executes a canonical transaction on the state it was executed on and reports it to the
+/// inspector, like `debug_traceTransaction`.
+/// The transactions before it in its block are executed without being inspected.
+pub fn trace_transaction<P, I>(
+    provider: &P,
+    spec: Arc<ChainSpec>,
+    hash: TxHash,
+    inspector: &mut I,
+) -> Result<Receipt, BlockExecutionError>
+where
+    P: StateProviderFactory
+        + TransactionsProvider
+        + BlockReader<Block = Block<TransactionSigned>, Header = Header>,
+    I: Inspector,
+{
+    let meta = provider
+        .transaction_block(hash)?
+        .ok_or(BlockExecutionError::TransactionNotFound(hash))?;
+    let block = provider
+        .block_by_number(meta.block_number)?
+        .ok_or(BlockExecutionError::TransactionNotFound(hash))?;
+
+    let state = State::new(provider.state_by_block_number(meta.block_number - 1)?);
+    let env = BlockEnv::from_header(block.sealed_header().header());
+    let mut executor = PintBlockExecutor::new(state, spec, env);
+    executor.prepare_execute()?;
+
+    let transactions = &block.body().transaction;
+    for (index, tx) in transactions.iter().enumerate() {
+        let tx: ExecutableTranasction = tx
+            .clone()
+            .try_into_recovered()
+            .map_err(|_| BlockValidationError::SenderRecovery(tx.hash()))?
+            .into();
+        if index == meta.index as usize {
+            return executor.inspect_transaction(&tx, inspector).cloned();
+        }
+        executor.inspect_transaction(&tx, &mut NoopInspector)?;
+    }
+    Err(BlockExecutionError::TransactionNotFound(hash))
+}
+
+#[cfg(test)]
+mod tests {
+    use storage::{
+        PintStateProviderFactory,
+        db::{DatabaseMut, InMemoryDB},
+        genesis::init_genesis,
+        traits::StateProviderFactory,
+    };
+
+    use crate::{
+        tests::{build_block, genesis, make_pool_transaction_1, test_env, test_spec},
+        traits::ExecutableTx,
+    };
+
+    use super::*;
+
+    fn sender() -> Address {
+        Address::from_hex("a24a188cdcb3bf5fc6ec498d2657c6066b242028".to_string()).unwrap()
+    }
+
+    fn receiver() -> Address {
+        Address::from_hex("e0aa4e80c739ee08b5a6680586d1bf3991840c21".to_string()).unwrap()
+    }
+
+    #[test]
+    fn test_tracers_record_transfer() {
+        let db = InMemoryDB::new();
+        init_genesis(&db, &genesis()).unwrap();
+        let state = State::new(PintStateProviderFactory::new(db.clone()).latest().unwrap());
+        let mut executor = PintBlockExecutor::new(state, test_spec(), test_env());
+        executor.prepare_execute().unwrap();
+
+        let tx = ExecutableTranasction::from_pool_transaction(make_pool_transaction_1());
+        let mut calls = CallTracer::default();
+        let mut diff = StateDiffTracer::default();
+        executor.inspect_transaction(&tx, &mut calls).unwrap();
+        // replaying the transaction fails and is left out of the state diff
+        let err = executor.inspect_transaction(&tx, &mut diff).unwrap_err();
+        assert!(matches!(
+            err,
+            BlockExecutionError::Validation(BlockValidationError::NonceMismatch { .. })
+        ));
+        executor.inspect_transaction(&tx, &mut calls).unwrap_err();
+        let mut other_chain =
+            ExecutableTranasction::from_pool_transaction(make_pool_transaction_1());
+        other_chain.chain_id += 1;
+        executor
+            .inspect_transaction(&other_chain, &mut calls)
+            .unwrap_err();
+
+        let traces = calls.into_json();
+        assert_eq!(traces[0]["success"], true);
+        assert_eq!(traces[0]["from"], hex_address(&sender()));
+        let ops: Vec<_> = traces[0]["steps"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|step| step["op"].as_str().unwrap())
+            .collect();
+        assert_eq!(ops, ["nonce", "fee", "balance", "balance"]);
+        assert_eq!(traces[0]["steps"][2]["to"], "8");
+        assert_eq!(traces[1]["success"], false);
+        assert!(
+            traces[1]["error"]
+                .as_str()
+                .unwrap()
+                .contains("NonceMismatch")
+        );
+        assert_eq!(traces[2]["steps"], json!([]));
+        assert!(
+            traces[2]["error"]
+                .as_str()
+                .unwrap()
+                .contains("ChainIdMismatch")
+        );
+        assert!(diff.diff().is_empty());
+    }
+
+    #[test]
+    fn test_trace_transaction() {
+        let db = InMemoryDB::new();
+        init_genesis(&db, &genesis()).unwrap();
+        let outcome = build_block(&db, test_spec());
+        let hash = outcome.receipts[0].tx_hash;
+        let expected = outcome.receipts[0].clone();
+        db.commit_block(outcome.into()).unwrap();
+
+        let provider = PintStateProviderFactory::new(db.clone());
+        let mut tracer = StateDiffTracer::default();
+        let receipt = trace_transaction(&provider, test_spec(), hash, &mut tracer).unwrap();
+        assert_eq!(receipt, expected);
+
+        let diff = tracer.diff();
+        assert_eq!(diff.len(), 2);
+        assert_eq!(
+            diff[&sender()],
+            AccountDiff {
+                balance: Some(Change {
+                    from: U256::from(10),
+                    to: U256::from(8)
+                }),
+                nonce: Some(Change { from: 0, to: 1 }),
+            }
+        );
+        let json = tracer.into_json();
+        assert_eq!(json[hex_address(&receiver())]["balance"]["to"], "1");
+
+        assert!(matches!(
+            trace_transaction(&provider, test_spec(), TxHash::ZERO, &mut NoopInspector),
+            Err(BlockExecutionError::TransactionNotFound(_))
+        ));
+    }
+}
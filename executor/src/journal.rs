@@ -0,0 +1,153 @@
+//! Journaled transition state.
+//! Every account write is recorded with the value it replaced, so all writes after a
+//! [Checkpoint] can be undone. Checkpoints nest: committing an inner checkpoint keeps its
+//! writes revertible by the outer one, and the journal is only dropped once the outermost
+//! checkpoint is committed.
+use std::collections::{HashMap, HashSet};
+
+use primitives::{account::Account, types::Address};
+
+// Account write and what it replaced
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct JournalEntry {
+    address: Address,
+    // None if the account didn't exist
+    previous: Option<Account>,
+    // whether this was the first write of the account since the state was loaded
+    first_touch: bool,
+}
+
+/// Position in the journal to revert to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Checkpoint {
+    journal_len: usize,
+}
+
+/// Accounts of the state being executed, with a journal of the writes since the open checkpoints
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct JournaledState {
+    accounts: HashMap<Address, Account>,
+    // accounts written since the state was loaded
+    touched: HashSet<Address>,
+    journal: Vec<JournalEntry>,
+    // number of open checkpoints
+    depth: usize,
+}
+
+impl JournaledState {
+    pub fn new(accounts: HashMap<Address, Account>) -> Self {
+        Self {
+            accounts,
+            ..Default::default()
+        }
+    }
+
+    pub fn get(&self, address: &Address) -> Option<&Account> {
+        self.accounts.get(address)
+    }
+
+    /// Writes the account, recording the replaced value if a checkpoint is open
+    pub fn set(&mut self, address: Address, account: Account) {
+        let first_touch = self.touched.insert(address.clone());
+        let previous = self.accounts.insert(address.clone(), account);
+        if self.depth > 0 {
+            self.journal.push(JournalEntry {
+                address,
+                previous,
+                first_touch,
+            });
+        }
+    }
+
+    /// Accounts written since the state was loaded
+    pub fn touched(&self) -> &HashSet<Address> {
+        &self.touched
+    }
+
+    pub fn checkpoint(&mut self) -> Checkpoint {
+        self.depth += 1;
+        Checkpoint {
+            journal_len: self.journal.len(),
+        }
+    }
+
+    /// Keeps the writes since the checkpoint
+    pub fn commit(&mut self, _checkpoint: Checkpoint) {
+        self.depth = self.depth.saturating_sub(1);
+        if self.depth == 0 {
+            self.journal.clear();
+        }
+    }
+
+    /// Undoes the writes since the checkpoint, newest first
+    pub fn revert_to(&mut self, checkpoint: Checkpoint) {
+        while self.journal.len() > checkpoint.journal_len {
+            let entry = self
+                .journal
+                .pop()
+                .expect("journal is longer than the checkpoint");
+            match entry.previous {
+                Some(account) => self.accounts.insert(entry.address.clone(), account),
+                None => self.accounts.remove(&entry.address),
+            };
+            if entry.first_touch {
+                self.touched.remove(&entry.address);
+            }
+        }
+        self.depth = self.depth.saturating_sub(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use primitives::types::U256;
+
+    use super::*;
+
+    fn account(balance: u64) -> Account {
+        Account {
+            nonce: 0,
+            balance: U256::from(balance),
+        }
+    }
+
+    #[test]
+    fn test_revert_restores_accounts() {
+        let alice = Address::from_byte([1; 20]);
+        let bob = Address::from_byte([2; 20]);
+        let mut state = JournaledState::new(HashMap::from([(alice.clone(), account(10))]));
+        let loaded = state.clone();
+
+        let checkpoint = state.checkpoint();
+        state.set(alice.clone(), account(5));
+        state.set(bob.clone(), account(5));
+        state.set(alice.clone(), account(4));
+        state.revert_to(checkpoint);
+
+        assert_eq!(state, loaded);
+        assert!(state.get(&bob).is_none());
+        assert!(state.touched().is_empty());
+    }
+
+    #[test]
+    fn test_nested_checkpoints() {
+        let alice = Address::from_byte([1; 20]);
+        let mut state = JournaledState::new(HashMap::from([(alice.clone(), account(10))]));
+
+        let outer = state.checkpoint();
+        state.set(alice.clone(), account(9));
+        let inner = state.checkpoint();
+        state.set(alice.clone(), account(8));
+        state.commit(inner);
+        // the outer checkpoint still reverts the committed inner writes
+        state.revert_to(outer);
+        assert_eq!(state.get(&alice), Some(&account(10)));
+
+        let checkpoint = state.checkpoint();
+        state.set(alice.clone(), account(7));
+        state.commit(checkpoint);
+        assert_eq!(state.get(&alice), Some(&account(7)));
+        assert!(state.touched().contains(&alice));
+        assert!(state.journal.is_empty());
+    }
+}
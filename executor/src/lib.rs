@@ -1,23 +1,100 @@
 pub mod database;
 pub mod error;
+pub mod import;
+pub mod inspector;
+pub mod journal;
+pub mod parallel;
+pub mod simulate;
+pub mod spec;
 pub mod traits;
 pub mod transaction;
 
-use primitives::block::{body::SealedBlock, traits::Block};
-use storage::traits::StateProvider;
+use std::{collections::HashMap, sync::Arc};
+
+use ::transaction::{TransactionSigned, traits::SignedTransaction};
+use primitives::{
+    account::Account,
+    block::{
+        Block,
+        body::{BlockBody, SealedBlock},
+        header::{Header, SealedHeader},
+    },
+    proofs::{calculate_receipts_root, calculate_transaction_root},
+    types::{Address, BlockHash, U256},
+};
+use storage::{db::ExecutedBlock, traits::StateProvider};
 
 use crate::{
-    database::State, error::BlockExecutionError, traits::BlockExecutor,
+    database::State,
+    error::{BlockExecutionError, BlockValidationError},
+    inspector::{Inspector, NoopInspector},
+    journal::Checkpoint,
+    spec::ChainSpec,
+    traits::BlockExecutor,
     transaction::ExecutableTranasction,
 };
 
+/// Header fields of the block being built, which don't depend on its transactions
+#[derive(Debug, Clone)]
+pub struct BlockEnv {
+    pub parent_hash: BlockHash,
+    pub number: u64,
+    pub timestamp: u64,
+    // account the fees and the reward of the block are paid to
+    pub beneficiary: Address,
+}
+
+impl BlockEnv {
+    /// Environment of a child block of `parent`
+    pub fn new(parent: &SealedHeader, timestamp: u64, beneficiary: Address) -> Self {
+        Self {
+            parent_hash: parent.hash(),
+            number: parent.number() + 1,
+            timestamp,
+            beneficiary,
+        }
+    }
+
+    /// Environment the given block was built in
+    pub fn from_header(header: &Header) -> Self {
+        Self {
+            parent_hash: header.previous_hash,
+            number: header.number,
+            timestamp: header.timestamp,
+            beneficiary: header.beneficiary.clone(),
+        }
+    }
+}
+
+/// Position of a [PintBlockExecutor] to roll back to
+#[derive(Debug, Clone, Copy)]
+pub struct ExecutorCheckpoint {
+    state: Checkpoint,
+    // number of executed transactions
+    transactions: usize,
+}
+
 /// Transaction executor Pint Main Executor
 pub struct PintBlockExecutor<DB> {
     pub state: State<DB>,
+    pub spec: Arc<ChainSpec>,
+    pub env: BlockEnv,
+    // executed transactions in block order
+    pub transactions: Vec<TransactionSigned>,
     pub receipts: Vec<Receipt>,
 }
 
 impl<DB: StateProvider> PintBlockExecutor<DB> {
+    pub fn new(state: State<DB>, spec: Arc<ChainSpec>, env: BlockEnv) -> Self {
+        Self {
+            state,
+            spec,
+            env,
+            transactions: Vec::new(),
+            receipts: Vec::new(),
+        }
+    }
+
     pub fn prepare_execute(&mut self) -> Result<(), BlockExecutionError> {
         let _ = self
             .state
@@ -25,6 +102,129 @@ impl<DB: StateProvider> PintBlockExecutor<DB> {
             .map_err(|_| BlockExecutionError::ExecutionError)?;
         Ok(())
     }
+
+    /// Marks the point the executed transactions can be rolled back to
+    pub fn checkpoint(&mut self) -> Result<ExecutorCheckpoint, BlockExecutionError> {
+        Ok(ExecutorCheckpoint {
+            state: self.state.checkpoint()?,
+            transactions: self.transactions.len(),
+        })
+    }
+
+    /// Keeps the transactions executed since the checkpoint
+    pub fn commit(&mut self, checkpoint: ExecutorCheckpoint) -> Result<(), BlockExecutionError> {
+        self.state.commit(checkpoint.state)
+    }
+
+    /// Drops the transactions executed since the checkpoint with their receipts and state changes
+    pub fn revert_to(&mut self, checkpoint: ExecutorCheckpoint) -> Result<(), BlockExecutionError> {
+        self.state.revert_to(checkpoint.state)?;
+        self.transactions.truncate(checkpoint.transactions);
+        self.receipts.truncate(checkpoint.transactions);
+        Ok(())
+    }
+
+    /// Executes the transaction like [BlockExecutor::execute_transaction], reporting its
+    /// steps to the inspector
+    pub fn inspect_transaction<I: Inspector>(
+        &mut self,
+        tx: &ExecutableTranasction,
+        inspector: &mut I,
+    ) -> Result<&Receipt, BlockExecutionError> {
+        let result = match check_chain_id(&self.spec, tx) {
+            Ok(()) => self.state.inspect_transaction(tx, inspector),
+            Err(err) => {
+                // rejected before it reaches the state, but reported like any rejected transaction
+                let result = Err(err.into());
+                inspector.transaction_start(tx);
+                inspector.transaction_end(tx, &result);
+                result
+            }
+        };
+        let mut receipt = result?;
+        if let Some(last) = self.receipts.last() {
+            receipt.cumulative_fee += last.cumulative_fee;
+        }
+        self.receipts.push(receipt);
+        self.transactions.push(tx.transaction.clone());
+        Ok(&self.receipts[self.receipts.len() - 1])
+    }
+
+    /// Whether the block holds more transactions than the chain spec allows
+    pub fn exceeds_block_limit(&self) -> bool {
+        self.spec
+            .max_block_transactions
+            .is_some_and(|limit| self.transactions.len() > limit)
+    }
+
+    /// Re-executes a received block on the state of its parent.
+    /// The outcome is only returned if it reproduces the block, so it can be committed as is.
+    /// The header is not checked against the parent, see [crate::import::BlockImporter].
+    pub fn execute_block(
+        state: State<DB>,
+        spec: Arc<ChainSpec>,
+        block: &SealedBlock<Block<TransactionSigned>>,
+    ) -> Result<BlockBuilderOutcome, BlockExecutionError> {
+        let header = block.sealed_header().header();
+        let env = BlockEnv::from_header(header);
+        let mut executor = Self::new(state, spec, env);
+        let transactions = &block.body().transaction;
+        if let Some(limit) = executor.spec.max_block_transactions
+            && transactions.len() > limit
+        {
+            return Err(BlockValidationError::TooManyTransactions {
+                limit,
+                got: transactions.len(),
+            }
+            .into());
+        }
+        let computed = calculate_transaction_root(transactions.iter().map(|tx| tx.hash()));
+        if computed != header.transaction_root {
+            return Err(BlockValidationError::TransactionRootMismatch {
+                expected: header.transaction_root,
+                computed,
+            }
+            .into());
+        }
+
+        let mut recovered = Vec::with_capacity(transactions.len());
+        for tx in transactions {
+            let tx = tx
+                .clone()
+                .try_into_recovered()
+                .map_err(|_| BlockValidationError::SenderRecovery(tx.hash()))?;
+            recovered.push(tx);
+        }
+        executor.prepare_execute()?;
+        for tx in recovered {
+            executor.execute_transaction(&tx.into())?;
+        }
+
+        let outcome = executor.finish()?;
+        let computed_header = outcome.block.sealed_header().header();
+        if computed_header.receipts_root != header.receipts_root {
+            return Err(BlockValidationError::ReceiptsRootMismatch {
+                expected: header.receipts_root,
+                computed: computed_header.receipts_root,
+            }
+            .into());
+        }
+        if computed_header.state_root != header.state_root {
+            return Err(BlockValidationError::StateRootMismatch {
+                expected: header.state_root,
+                computed: computed_header.state_root,
+            }
+            .into());
+        }
+        // the remaining fields are copied from the header, so this only fails if the header
+        // gains fields the executor doesn't reproduce
+        let expected = block.sealed_header().hash();
+        let computed = outcome.block.sealed_header().hash();
+        if computed != expected {
+            return Err(BlockValidationError::BlockHashMismatch { expected, computed }.into());
+        }
+        Ok(outcome)
+    }
 }
 
 impl<DB: StateProvider> BlockExecutor for PintBlockExecutor<DB> {
@@ -34,39 +234,107 @@ impl<DB: StateProvider> BlockExecutor for PintBlockExecutor<DB> {
         &mut self,
         tx: &Self::Transaction,
     ) -> Result<Option<u64>, BlockExecutionError> {
-        let receipt = match self.state.execute_transaction(tx) {
-            Ok(receipt) => receipt,
-            Err(err) => return Err(err),
-        };
-        self.receipts.push(receipt);
+        self.inspect_transaction(tx, &mut NoopInspector)?;
         Ok(Some(0))
     }
 
+    // Transactions are applied to the transition state as they execute, so there is nothing
+    // left to commit.
     fn execute_and_commit(
         &mut self,
         tx: &Self::Transaction,
     ) -> Result<Option<u64>, BlockExecutionError> {
-        todo!()
+        self.execute_transaction(tx)
     }
 
-    fn finish<B: Block>(&self) -> Result<BlockBuilderOutcome<B>, BlockExecutionError> {
-        todo!()
+    fn finish(mut self) -> Result<BlockBuilderOutcome, BlockExecutionError> {
+        // Post-block system step: the beneficiary receives the fees of the block, minus the
+        // burned part, and the block reward.
+        let total_fees = self
+            .receipts
+            .last()
+            .map_or(U256::ZERO, |receipt| receipt.cumulative_fee);
+        let burned_fees = self.spec.burned_fees(total_fees);
+        let fees = total_fees - burned_fees;
+        let reward = self.spec.block_reward(self.env.number);
+        let payout = fees
+            .checked_add(reward)
+            .ok_or_else(|| BlockValidationError::BalanceOverflow(self.env.beneficiary.clone()))?;
+        if !payout.is_zero() {
+            self.state
+                .increment_balance(&self.env.beneficiary, payout)?;
+        }
+
+        let state_diff = self.state.diff()?;
+        let state_root = self.state.database.state_root(&state_diff)?;
+        let header = Header {
+            previous_hash: self.env.parent_hash,
+            number: self.env.number,
+            transaction_root: calculate_transaction_root(
+                self.transactions.iter().map(|tx| tx.hash()),
+            ),
+            state_root,
+            receipts_root: calculate_receipts_root(&self.receipts),
+            timestamp: self.env.timestamp,
+            beneficiary: self.env.beneficiary,
+        };
+        let body = BlockBody {
+            transaction: self.transactions,
+        };
+
+        Ok(BlockBuilderOutcome {
+            receipts: self.receipts,
+            block: SealedBlock::new(SealedHeader::seal_slow(header), body),
+            state_diff,
+            fees,
+            burned_fees,
+            reward,
+        })
+    }
+}
+
+// Transactions signed for another chain are rejected
+pub(crate) fn check_chain_id(
+    spec: &ChainSpec,
+    tx: &ExecutableTranasction,
+) -> Result<(), BlockValidationError> {
+    if tx.chain_id != spec.chain_id {
+        return Err(BlockValidationError::ChainIdMismatch {
+            expected: spec.chain_id,
+            got: tx.chain_id,
+        });
     }
+    Ok(())
 }
 
 /// In this project, executor can be builder, so we deal with this in the executor struct
-pub struct BlockBuilderOutcome<B: Block> {
+pub struct BlockBuilderOutcome {
     pub receipts: Vec<Receipt>,
-    pub block: SealedBlock<B>,
+    pub block: SealedBlock<Block<TransactionSigned>>,
+    // post-state of the accounts the block changed
+    pub state_diff: HashMap<Address, Account>,
+    // fees paid to the beneficiary
+    pub fees: U256,
+    // fees burned according to the chain spec
+    pub burned_fees: U256,
+    // newly issued subsidy paid to the beneficiary
+    pub reward: U256,
 }
 
-/// Transaction Execution Result for BlockExecutor
-#[derive(Default, Debug)]
-pub struct Receipt {
-    tx_type: u8,
-    success: bool,
+impl From<BlockBuilderOutcome> for ExecutedBlock {
+    fn from(outcome: BlockBuilderOutcome) -> Self {
+        let (header, body) = outcome.block.split();
+        ExecutedBlock::new(
+            header.into_header(),
+            body,
+            outcome.receipts,
+            outcome.state_diff,
+        )
+    }
 }
 
+pub use primitives::receipt::Receipt;
+
 #[cfg(test)]
 mod tests {
 
@@ -75,10 +343,12 @@ mod tests {
         traits::{Decodable, SignedTransaction},
         transaction::TxEnvelope,
     };
-    use primitives::types::Address;
+    use primitives::types::{Address, B256};
     use storage::{
         PintStateProviderFactory,
-        db::InMemoryDB,
+        check::check_database,
+        db::{Database, DatabaseMut, InMemoryDB},
+        genesis::{Genesis, GenesisAccount, init_genesis},
         traits::{StateProviderBox, StateProviderFactory},
     };
     use transaction_pool::{
@@ -112,7 +382,26 @@ mod tests {
         (pool, db, provider)
     }
 
-    fn make_pool_transaction_1() -> PintPooledTransaction {
+    pub(crate) fn test_env() -> BlockEnv {
+        BlockEnv {
+            parent_hash: BlockHash::ZERO,
+            number: 1,
+            timestamp: 0,
+            beneficiary: test_beneficiary(),
+        }
+    }
+
+    pub(crate) fn test_beneficiary() -> Address {
+        Address::from_byte([0xbe; 20])
+    }
+
+    // Chain of the test transactions, which burns half of the fees
+    pub(crate) fn test_spec() -> Arc<ChainSpec> {
+        let spec = ChainSpec::default().with_fee_burn_percent(50);
+        Arc::new(spec)
+    }
+
+    pub(crate) fn make_pool_transaction_1() -> PintPooledTransaction {
         // sender: a24a188cdcb3bf5fc6ec498d2657c6066b242028, receiver: e0aa4e80c739ee08b5a6680586d1bf3991840c21, fee: 1, value: 1, nonce: 0
         let raw = "0000000000000000000000000000000000e0aa4e80c739ee08b5a6680586d1bf3991840c21000000000000000000000000000000010000000000000000000000000000000000000000000000000000000000000001be2855167f254060b5812e4a2849c7ba3d34ea4aeb175e87f83c2a7c1424379a6e722511c17cb5191e090b2a75dfe2b924d2b1bcbf0a2f26e207cb728dcaa34501";
         let data = hex::decode(raw).unwrap();
@@ -156,10 +445,7 @@ mod tests {
         // State 실행을 위해서 wrapping (Provider + Transition_State(모의 실행용))
         let state = State::new(state_provider);
         // make the executor (State + 영수증 용)
-        let mut executor = PintBlockExecutor {
-            state,
-            receipts: Vec::new(),
-        };
+        let mut executor = PintBlockExecutor::new(state, test_spec(), test_env());
 
         // add_transaction
         let tx1 = make_pool_transaction_1();
@@ -181,7 +467,7 @@ mod tests {
         executor.prepare_execute().unwrap();
 
         for tx in txs.iter() {
-            let res = executor.execute_transaction(tx);
+            executor.execute_transaction(tx).unwrap();
         }
 
         let binding = executor.state.transition_state.expect("None");
@@ -196,7 +482,8 @@ mod tests {
                 &Address::from_hex("a24a188cdcb3bf5fc6ec498d2657c6066b242028".to_string()).unwrap(),
             )
             .unwrap();
-        assert!(sender1.balance == U256::from(9));
+        // value and fee are paid
+        assert!(sender1.balance == U256::from(8));
         assert!(receiver1.balance == U256::from(1));
         assert!(sender1.nonce == 1);
 
@@ -210,11 +497,19 @@ mod tests {
                 &Address::from_hex("314f3ea92a6fc23d6b66057d3acfba04d6b08b58".to_string()).unwrap(),
             )
             .unwrap();
-        assert!(sender2.balance == U256::from(9));
+        assert!(sender2.balance == U256::from(8));
         assert!(receiver2.balance == U256::from(1));
         assert!(sender2.nonce == 1);
 
-        dbg!(executor.receipts);
+        let receipts = &executor.receipts;
+        assert_eq!(receipts.len(), 2);
+        assert_eq!(receipts[0].tx_hash, txs[0].hash);
+        assert_eq!(receipts[0].fee, U256::from(1));
+        assert_eq!(receipts[1].cumulative_fee, U256::from(2));
+        assert_eq!(
+            (receipts[1].sender_nonce, receipts[1].receiver_nonce),
+            (1, 0)
+        );
     }
 
     #[tokio::test]
@@ -235,10 +530,7 @@ mod tests {
         // State 실행을 위해서 wrapping (Provider + Transition_State(모의 실행용))
         let state = State::new(state_provider);
         // make the executor (State + 영수증 용)
-        let mut executor = PintBlockExecutor {
-            state,
-            receipts: Vec::new(),
-        };
+        let mut executor = PintBlockExecutor::new(state, test_spec(), test_env());
 
         // add_transaction
         let tx1 = make_pool_transaction_1();
@@ -256,4 +548,328 @@ mod tests {
 
         assert!(txs.len() == 0);
     }
+
+    pub(crate) fn genesis() -> Genesis {
+        let account = GenesisAccount {
+            balance: U256::from(10),
+            ..Default::default()
+        };
+        Genesis::default()
+            .with_account(
+                Address::from_hex("a24a188cdcb3bf5fc6ec498d2657c6066b242028".to_string()).unwrap(),
+                account.clone(),
+            )
+            .with_account(
+                Address::from_hex("314f3ea92a6fc23d6b66057d3acfba04d6b08b58".to_string()).unwrap(),
+                account,
+            )
+    }
+
+    // Builds block 1 with both test transactions on top of the genesis block.
+    pub(crate) fn build_block(db: &InMemoryDB, spec: Arc<ChainSpec>) -> BlockBuilderOutcome {
+        let state = State::new(PintStateProviderFactory::new(db.clone()).latest().unwrap());
+        let env = BlockEnv::new(&genesis().header(), 7, test_beneficiary());
+        let mut executor = PintBlockExecutor::new(state, spec, env);
+        executor.prepare_execute().unwrap();
+        for tx in [make_pool_transaction_1(), make_pool_transaction_2()] {
+            let tx = ExecutableTranasction::from_pool_transaction(tx);
+            executor.execute_transaction(&tx).unwrap();
+        }
+        executor.finish().unwrap()
+    }
+
+    #[test]
+    fn test_finish_seals_block_with_roots() {
+        let db = InMemoryDB::new();
+        let genesis_hash = init_genesis(&db, &genesis()).unwrap();
+        let outcome = build_block(&db, test_spec());
+
+        let header = outcome.block.sealed_header().header().clone();
+        assert_eq!(
+            (header.previous_hash, header.number, header.timestamp),
+            (genesis_hash, 1, 7)
+        );
+        let transactions = &outcome.block.body().transaction;
+        assert_eq!(transactions.len(), 2);
+        assert_eq!(
+            header.transaction_root,
+            calculate_transaction_root(transactions.iter().map(|tx| tx.hash()))
+        );
+        assert_eq!(
+            header.receipts_root,
+            calculate_receipts_root(&outcome.receipts)
+        );
+        // both senders, both receivers and the beneficiary
+        assert_eq!(outcome.state_diff.len(), 5);
+
+        db.commit_block(outcome.into()).unwrap();
+        assert_eq!(db.block_number(), 1);
+        // the committed block matches its roots and the stored state
+        let report = check_database(&db).unwrap();
+        assert!(report.is_ok(), "{:?}", report.findings);
+    }
+
+    #[test]
+    fn test_execute_block_reproduces_built_block() {
+        let builder = InMemoryDB::new();
+        init_genesis(&builder, &genesis()).unwrap();
+        let block = build_block(&builder, test_spec()).block;
+
+        let db = InMemoryDB::new();
+        init_genesis(&db, &genesis()).unwrap();
+        let provider = PintStateProviderFactory::new(db.clone());
+        let outcome = PintBlockExecutor::execute_block(
+            State::new(provider.latest().unwrap()),
+            test_spec(),
+            &block,
+        )
+        .unwrap();
+        assert_eq!(
+            outcome.block.sealed_header().hash(),
+            block.sealed_header().hash()
+        );
+
+        // a header that does not commit to the executed state is rejected
+        let (header, body) = block.split();
+        let header = Header {
+            state_root: B256::ZERO,
+            ..header.into_header()
+        };
+        let tampered = SealedBlock::new(SealedHeader::seal_slow(header), body);
+        assert!(matches!(
+            PintBlockExecutor::execute_block(
+                State::new(provider.latest().unwrap()),
+                test_spec(),
+                &tampered,
+            ),
+            Err(BlockExecutionError::Validation(
+                BlockValidationError::StateRootMismatch { expected, .. }
+            )) if expected == B256::ZERO
+        ));
+    }
+
+    #[test]
+    fn test_invalid_transactions_leave_state_untouched() {
+        let db = InMemoryDB::new();
+        init_genesis(&db, &genesis()).unwrap();
+        let state = State::new(PintStateProviderFactory::new(db.clone()).latest().unwrap());
+        let mut executor = PintBlockExecutor::new(state, test_spec(), test_env());
+        executor.prepare_execute().unwrap();
+        let before = executor.state.transition_state.clone();
+        let valid = || ExecutableTranasction::from_pool_transaction(make_pool_transaction_1());
+
+        let mut tx = valid();
+        tx.nonce = 1;
+        assert!(matches!(
+            executor.execute_transaction(&tx),
+            Err(BlockExecutionError::Validation(
+                BlockValidationError::NonceMismatch {
+                    expected: 0,
+                    got: 1
+                }
+            ))
+        ));
+
+        let mut tx = valid();
+        tx.value = U256::from(10);
+        assert!(matches!(
+            executor.execute_transaction(&tx),
+            Err(BlockExecutionError::Validation(
+                BlockValidationError::InsufficientBalance { balance, cost }
+            )) if balance == U256::from(10) && cost == U256::from(11)
+        ));
+
+        let mut tx = valid();
+        tx.value = U256::MAX;
+        assert!(matches!(
+            executor.execute_transaction(&tx),
+            Err(BlockExecutionError::Validation(
+                BlockValidationError::CostOverflow
+            ))
+        ));
+
+        let mut tx = valid();
+        tx.chain_id = 1;
+        assert!(matches!(
+            executor.execute_transaction(&tx),
+            Err(BlockExecutionError::Validation(
+                BlockValidationError::ChainIdMismatch {
+                    expected: 0,
+                    got: 1
+                }
+            ))
+        ));
+
+        assert_eq!(executor.state.transition_state, before);
+        assert!(executor.state.diff().unwrap().is_empty());
+        assert!(executor.receipts.is_empty() && executor.transactions.is_empty());
+
+        // the sender can still spend its whole balance on a valid transaction
+        let mut tx = valid();
+        tx.value = U256::from(9);
+        executor.execute_transaction(&tx).unwrap();
+        // a replay has a stale nonce
+        assert!(matches!(
+            executor.execute_transaction(&tx),
+            Err(BlockExecutionError::Validation(
+                BlockValidationError::NonceMismatch {
+                    expected: 1,
+                    got: 0
+                }
+            ))
+        ));
+
+        // a sender at the last nonce cannot send anymore
+        let mut tx = valid();
+        let account = Account {
+            nonce: u64::MAX,
+            balance: U256::from(10),
+        };
+        executor
+            .state
+            .set_account(tx.sender.clone(), account)
+            .unwrap();
+        tx.nonce = u64::MAX;
+        let receipts = executor.receipts.len();
+        assert!(matches!(
+            executor.execute_transaction(&tx),
+            Err(BlockExecutionError::Validation(
+                BlockValidationError::NonceOverflow
+            ))
+        ));
+        assert_eq!(executor.receipts.len(), receipts);
+    }
+
+    // Sum of all balances at the block
+    fn supply(db: &InMemoryDB, number: u64) -> U256 {
+        db.copy_state_from_block_no(number)
+            .unwrap()
+            .values()
+            .fold(U256::ZERO, |supply, account| supply + account.balance)
+    }
+
+    #[test]
+    fn test_fees_are_paid_to_the_beneficiary() {
+        let db = InMemoryDB::new();
+        init_genesis(&db, &genesis()).unwrap();
+        let outcome = build_block(&db, test_spec());
+
+        // both transactions pay a fee of 1 and half of the fees is burned
+        assert_eq!(outcome.fees, U256::from(1));
+        assert_eq!(outcome.burned_fees, U256::from(1));
+        let paid = outcome
+            .receipts
+            .iter()
+            .fold(U256::ZERO, |paid, receipt| paid + receipt.fee);
+        assert_eq!(paid, outcome.fees + outcome.burned_fees);
+        let beneficiary = test_beneficiary();
+        assert_eq!(outcome.state_diff[&beneficiary].balance, outcome.fees);
+        assert_eq!(
+            outcome.block.sealed_header().header().beneficiary,
+            beneficiary
+        );
+
+        let burned = outcome.burned_fees;
+        db.commit_block(outcome.into()).unwrap();
+        assert_eq!(supply(&db, 0) - supply(&db, 1), burned);
+    }
+
+    #[test]
+    fn test_block_reward_is_issued() {
+        let db = InMemoryDB::new();
+        init_genesis(&db, &genesis()).unwrap();
+        let spec = ChainSpec::clone(&test_spec()).with_block_reward(U256::from(1_000), 1);
+        let spec = Arc::new(spec);
+        let outcome = build_block(&db, spec.clone());
+
+        assert_eq!(outcome.reward, U256::from(1_000));
+        assert_eq!(
+            outcome.state_diff[&test_beneficiary()].balance,
+            outcome.fees + outcome.reward
+        );
+
+        let burned = outcome.burned_fees;
+        db.commit_block(outcome.into()).unwrap();
+        // the supply grows by the issuance and shrinks by the burned fees
+        assert_eq!(
+            supply(&db, 1) + burned,
+            supply(&db, 0) + spec.issued_supply(1)
+        );
+    }
+
+    #[test]
+    fn test_failed_transfer_is_reverted() {
+        // the receiver of the first test transaction cannot be credited
+        let receiver =
+            Address::from_hex("e0aa4e80c739ee08b5a6680586d1bf3991840c21".to_string()).unwrap();
+        let genesis = genesis().with_account(
+            receiver.clone(),
+            GenesisAccount {
+                balance: U256::MAX,
+                ..Default::default()
+            },
+        );
+        let db = InMemoryDB::new();
+        init_genesis(&db, &genesis).unwrap();
+        let state = State::new(PintStateProviderFactory::new(db.clone()).latest().unwrap());
+        let mut executor = PintBlockExecutor::new(state, test_spec(), test_env());
+        executor.prepare_execute().unwrap();
+        let before = executor.state.transition_state.clone();
+
+        let tx = ExecutableTranasction::from_pool_transaction(make_pool_transaction_1());
+        assert!(matches!(
+            executor.execute_transaction(&tx),
+            Err(BlockExecutionError::Validation(
+                BlockValidationError::BalanceOverflow(address)
+            )) if address == receiver
+        ));
+        // the sender was debited before the receiver failed, and that write is undone
+        assert_eq!(executor.state.transition_state, before);
+    }
+
+    #[test]
+    fn test_revert_to_rolls_back_transactions() {
+        let db = InMemoryDB::new();
+        init_genesis(&db, &genesis()).unwrap();
+        let spec = ChainSpec::clone(&test_spec()).with_max_block_transactions(1);
+        let state = State::new(PintStateProviderFactory::new(db.clone()).latest().unwrap());
+        let env = BlockEnv::new(&genesis().header(), 7, test_beneficiary());
+        let mut executor = PintBlockExecutor::new(state, Arc::new(spec), env);
+        executor.prepare_execute().unwrap();
+
+        // fill the block like the payload builder does
+        let mut included = Vec::new();
+        for tx in [make_pool_transaction_1(), make_pool_transaction_2()] {
+            let tx = ExecutableTranasction::from_pool_transaction(tx);
+            let checkpoint = executor.checkpoint().unwrap();
+            executor.execute_transaction(&tx).unwrap();
+            if executor.exceeds_block_limit() {
+                executor.revert_to(checkpoint).unwrap();
+                break;
+            }
+            executor.commit(checkpoint).unwrap();
+            included.push(tx.hash);
+        }
+        assert_eq!(included.len(), 1);
+        assert_eq!(executor.receipts.len(), 1);
+
+        let outcome = executor.finish().unwrap();
+        // only the sender and receiver of the first transaction and the beneficiary changed
+        assert_eq!(outcome.state_diff.len(), 3);
+        let second_sender =
+            Address::from_hex("314f3ea92a6fc23d6b66057d3acfba04d6b08b58".to_string()).unwrap();
+        assert!(!outcome.state_diff.contains_key(&second_sender));
+        assert_eq!(outcome.block.body().transaction[0].hash(), included[0]);
+
+        // importing a block above the limit fails before executing it
+        let full_block = build_block(&db, test_spec()).block;
+        let spec = ChainSpec::clone(&test_spec()).with_max_block_transactions(1);
+        let state = State::new(PintStateProviderFactory::new(db.clone()).latest().unwrap());
+        assert!(matches!(
+            PintBlockExecutor::execute_block(state, Arc::new(spec), &full_block),
+            Err(BlockExecutionError::Validation(
+                BlockValidationError::TooManyTransactions { limit: 1, got: 2 }
+            ))
+        ));
+    }
 }
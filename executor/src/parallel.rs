@@ -0,0 +1,328 @@
+//! Parallel execution of the transactions of a block.
+//! A transfer only reads and writes its sender and receiver, so transactions are split into
+//! groups that share no account: transactions of one group run in block order, and the groups
+//! run side by side on their own copy of the accounts they touch. The writes of the groups are
+//! disjoint, so merging them gives the same state and receipts as executing the block in order.
+use std::{collections::HashMap, num::NonZeroUsize, thread};
+
+use primitives::{account::Account, types::Address};
+use storage::traits::StateProvider;
+
+use crate::{
+    PintBlockExecutor, Receipt, check_chain_id, database::transact, error::BlockExecutionError,
+    inspector::NoopInspector, journal::JournaledState, transaction::ExecutableTranasction,
+};
+
+/// Splits the transactions into groups that share no account.
+/// Every group holds indices into `txs` in block order, and the groups are ordered by their
+/// first transaction.
+pub fn partition(txs: &[ExecutableTranasction]) -> Vec<Vec<usize>> {
+    // union-find over the transactions, joined through the accounts they touch
+    let mut parent: Vec<usize> = (0..txs.len()).collect();
+    let mut owner: HashMap<&Address, usize> = HashMap::new();
+    for (index, tx) in txs.iter().enumerate() {
+        for address in [&tx.sender, &tx.receiver] {
+            match owner.get(address) {
+                Some(&other) => union(&mut parent, index, other),
+                None => {
+                    owner.insert(address, index);
+                }
+            }
+        }
+    }
+
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    let mut group_of_root = HashMap::new();
+    for index in 0..txs.len() {
+        let root = find(&mut parent, index);
+        let group = *group_of_root.entry(root).or_insert_with(|| {
+            groups.push(Vec::new());
+            groups.len() - 1
+        });
+        groups[group].push(index);
+    }
+    groups
+}
+
+fn find(parent: &mut [usize], mut index: usize) -> usize {
+    while parent[index] != index {
+        parent[index] = parent[parent[index]];
+        index = parent[index];
+    }
+    index
+}
+
+fn union(parent: &mut [usize], a: usize, b: usize) {
+    let (a, b) = (find(parent, a), find(parent, b));
+    // the smaller index becomes the root so the roots stay stable
+    parent[a.max(b)] = a.min(b);
+}
+
+// Outcome of one group: receipts by transaction index and the post-state of the written
+// accounts, or the first failing transaction of the group
+type GroupResult =
+    Result<(Vec<(usize, Receipt)>, Vec<(Address, Account)>), (usize, BlockExecutionError)>;
+
+impl<DB: StateProvider> PintBlockExecutor<DB> {
+    /// Executes the transactions like [crate::traits::BlockExecutor::execute_transaction] in
+    /// order, running transactions that share no account on up to `threads` threads.
+    ///
+    /// The batch is applied as a whole: if a transaction is invalid, the error of the first
+    /// invalid transaction in block order is returned and nothing is executed.
+    pub fn execute_transactions_parallel(
+        &mut self,
+        txs: &[ExecutableTranasction],
+        threads: NonZeroUsize,
+    ) -> Result<(), BlockExecutionError> {
+        let state = self
+            .state
+            .transition_state
+            .as_ref()
+            .ok_or(BlockExecutionError::StateNotPrepared)?;
+
+        // every group starts from the accounts it touches
+        let groups: Vec<_> = partition(txs)
+            .into_iter()
+            .map(|group| {
+                let accounts = group
+                    .iter()
+                    .flat_map(|&index| [&txs[index].sender, &txs[index].receiver])
+                    .filter_map(|address| Some((address.clone(), *state.get(address)?)))
+                    .collect();
+                (group, JournaledState::new(accounts))
+            })
+            .collect();
+
+        // deal the groups round-robin to the workers
+        let workers = threads.get().min(groups.len()).max(1);
+        let mut batches: Vec<Vec<_>> = (0..workers).map(|_| Vec::new()).collect();
+        for (position, group) in groups.into_iter().enumerate() {
+            batches[position % workers].push(group);
+        }
+
+        let spec = &self.spec;
+        let results: Vec<GroupResult> = thread::scope(|scope| {
+            let handles: Vec<_> = batches
+                .into_iter()
+                .map(|batch| {
+                    scope.spawn(move || {
+                        batch
+                            .into_iter()
+                            .map(|(group, mut state)| {
+                                let mut receipts = Vec::with_capacity(group.len());
+                                for index in group {
+                                    let receipt = check_chain_id(spec, &txs[index])
+                                        .map_err(BlockExecutionError::from)
+                                        .and_then(|_| {
+                                            transact(&mut state, &txs[index], &mut NoopInspector)
+                                        })
+                                        .map_err(|err| (index, err))?;
+                                    receipts.push((index, receipt));
+                                }
+                                let written = state
+                                    .touched()
+                                    .iter()
+                                    .filter_map(|address| {
+                                        Some((address.clone(), *state.get(address)?))
+                                    })
+                                    .collect();
+                                Ok((receipts, written))
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().expect("execution worker panicked"))
+                .collect()
+        });
+
+        let mut receipts = Vec::with_capacity(txs.len());
+        let mut written = Vec::new();
+        let mut first_error: Option<(usize, BlockExecutionError)> = None;
+        for result in results {
+            match result {
+                Ok((group_receipts, group_written)) => {
+                    receipts.extend(group_receipts);
+                    written.extend(group_written);
+                }
+                Err((index, err)) => {
+                    if first_error.as_ref().is_none_or(|(first, _)| index < *first) {
+                        first_error = Some((index, err));
+                    }
+                }
+            }
+        }
+        if let Some((_, err)) = first_error {
+            return Err(err);
+        }
+
+        // the groups wrote disjoint accounts, so the order of the writes doesn't matter
+        for (address, account) in written {
+            self.state.set_account(address, account)?;
+        }
+        receipts.sort_unstable_by_key(|(index, _)| *index);
+        for (index, mut receipt) in receipts {
+            if let Some(last) = self.receipts.last() {
+                receipt.cumulative_fee += last.cumulative_fee;
+            }
+            self.receipts.push(receipt);
+            self.transactions.push(txs[index].transaction.clone());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use primitives::{
+        signature::Signature,
+        types::{B256, U256},
+    };
+    use proptest::prelude::*;
+    use storage::{
+        PintStateProviderFactory,
+        db::InMemoryDB,
+        genesis::{Genesis, GenesisAccount, init_genesis},
+        traits::StateProviderFactory,
+    };
+    use transaction::{PintTx, signed::Signed, transaction::TxEnvelope};
+
+    use crate::{
+        BlockEnv, database::State, spec::ChainSpec, traits::BlockExecutor,
+        transaction::ExecutableTranasction,
+    };
+
+    use super::*;
+
+    const ACCOUNTS: u8 = 8;
+
+    fn address(index: u8) -> Address {
+        Address::from_byte([index + 1; 20])
+    }
+
+    // Transfer with a placeholder signature, executors trust the given sender
+    fn transfer(
+        index: usize,
+        sender: u8,
+        receiver: u8,
+        nonce: u64,
+        value: u64,
+    ) -> ExecutableTranasction {
+        let tx = PintTx {
+            chain_id: 0,
+            nonce,
+            to: address(receiver),
+            fee: 1,
+            value: U256::from(value),
+        };
+        let hash = B256::from(U256::from(index));
+        let signature = Signature::from_bytes_and_parity(&[0; 64], false);
+        ExecutableTranasction {
+            tx_type: 0,
+            hash,
+            chain_id: tx.chain_id,
+            sender: address(sender),
+            receiver: tx.to.clone(),
+            nonce,
+            value: tx.value,
+            fee: U256::from(tx.fee),
+            transaction: TxEnvelope::Pint(Signed::new(tx, signature, hash)),
+        }
+    }
+
+    // Transfers between the test accounts with the nonces each sender expects
+    fn transfers(pairs: &[(u8, u8, u64)]) -> Vec<ExecutableTranasction> {
+        let mut nonces = HashMap::new();
+        pairs
+            .iter()
+            .enumerate()
+            .map(|(index, &(sender, receiver, value))| {
+                let nonce = nonces.entry(sender).or_insert(0);
+                *nonce += 1;
+                transfer(index, sender, receiver, *nonce - 1, value)
+            })
+            .collect()
+    }
+
+    fn executor(db: &InMemoryDB) -> PintBlockExecutor<storage::traits::StateProviderBox> {
+        let account = GenesisAccount {
+            balance: U256::from(20),
+            ..Default::default()
+        };
+        let genesis = (0..ACCOUNTS).fold(Genesis::default(), |genesis, index| {
+            genesis.with_account(address(index), account.clone())
+        });
+        init_genesis(db, &genesis).unwrap();
+        let state = State::new(PintStateProviderFactory::new(db.clone()).latest().unwrap());
+        let spec = ChainSpec::default().with_fee_burn_percent(50);
+        let env = BlockEnv::new(&genesis.header(), 1, address(0));
+        let mut executor = PintBlockExecutor::new(state, Arc::new(spec), env);
+        executor.prepare_execute().unwrap();
+        executor
+    }
+
+    // Executes the transactions in order and in parallel and checks both give the same block
+    fn assert_same_as_sequential(txs: &[ExecutableTranasction], threads: usize) {
+        let mut sequential = executor(&InMemoryDB::new());
+        let expected = txs
+            .iter()
+            .try_for_each(|tx| sequential.execute_transaction(tx).map(|_| ()));
+
+        let mut parallel = executor(&InMemoryDB::new());
+        let threads = NonZeroUsize::new(threads).unwrap();
+        let got = parallel.execute_transactions_parallel(txs, threads);
+
+        match (expected, got) {
+            (Ok(()), Ok(())) => {
+                let expected = sequential.finish().unwrap();
+                let got = parallel.finish().unwrap();
+                assert_eq!(got.receipts, expected.receipts);
+                assert_eq!(got.state_diff, expected.state_diff);
+                assert_eq!(
+                    got.block.sealed_header().hash(),
+                    expected.block.sealed_header().hash()
+                );
+            }
+            (Err(expected), Err(got)) => {
+                assert_eq!(format!("{got:?}"), format!("{expected:?}"));
+                // nothing of the failed batch is applied
+                assert!(parallel.receipts.is_empty());
+                assert!(parallel.state.diff().unwrap().is_empty());
+            }
+            (expected, got) => panic!("sequential: {expected:?}, parallel: {got:?}"),
+        }
+    }
+
+    #[test]
+    fn test_partition_groups_shared_accounts() {
+        let txs = transfers(&[(1, 2, 1), (3, 4, 1), (2, 5, 1), (6, 6, 1), (5, 3, 1)]);
+        assert_eq!(partition(&txs), vec![vec![0, 1, 2, 4], vec![3]]);
+
+        let txs = transfers(&[(1, 2, 1), (3, 4, 1), (5, 6, 1)]);
+        assert_eq!(partition(&txs), vec![vec![0], vec![1], vec![2]]);
+        assert!(partition(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_first_invalid_transaction_in_block_order_is_reported() {
+        // both groups fail, the second transaction overdraws before the fourth
+        let txs = transfers(&[(1, 2, 1), (3, 4, 100), (5, 6, 1), (1, 2, 100)]);
+        assert_same_as_sequential(&txs, 2);
+    }
+
+    proptest! {
+        // Differential test: random blocks give the same receipts, state and header in both
+        // executors, including blocks with conflicting and invalid transfers
+        #[test]
+        fn test_parallel_matches_sequential(
+            pairs in prop::collection::vec((0..ACCOUNTS, 0..ACCOUNTS, 0u64..12), 0..40),
+            threads in 1usize..6,
+        ) {
+            assert_same_as_sequential(&transfers(&pairs), threads);
+        }
+    }
+}
@@ -0,0 +1,152 @@
+//! Dry-run of transactions.
+//! A transaction is executed on a read-only state provider, so wallets can preview its fee,
+//! the resulting balances and why it would be rejected before signing it.
+use std::{collections::HashMap, sync::Arc};
+
+use ::transaction::transaction::Transaction;
+use primitives::{account::Account, types::Address};
+use storage::traits::StateProviderBox;
+
+use crate::{
+    Receipt, check_chain_id, database::State, error::BlockExecutionError, spec::ChainSpec,
+    transaction::ExecutableTranasction,
+};
+
+/// Effect of a simulated transaction
+#[derive(Debug, Clone)]
+pub struct SimulationOutcome {
+    pub receipt: Receipt,
+    // post-state of the accounts the transaction changed
+    pub state_diff: HashMap<Address, Account>,
+}
+
+/// Executes the unsigned transaction as if `sender` signed it, on the state the provider reads.
+/// Nothing is written: the changes live in the transition state, which is dropped afterwards.
+/// Block level payouts to the beneficiary are not part of the outcome.
+pub fn simulate_transaction(
+    state: StateProviderBox,
+    spec: Arc<ChainSpec>,
+    tx: Transaction,
+    sender: Address,
+) -> Result<SimulationOutcome, BlockExecutionError> {
+    let tx = ExecutableTranasction::from_unsigned(tx, sender);
+    check_chain_id(&spec, &tx)?;
+
+    let mut state = State::new(state);
+    state
+        .prepare_execute()
+        .map_err(|_| BlockExecutionError::ExecutionError)?;
+    let receipt = state.execute_transaction(&tx)?;
+    Ok(SimulationOutcome {
+        receipt,
+        state_diff: state.diff()?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use ::transaction::{PintTx, U256};
+    use storage::{
+        PintStateProviderFactory,
+        db::{Database, DatabaseMut, InMemoryDB},
+        genesis::init_genesis,
+        traits::StateProviderFactory,
+    };
+
+    use crate::{
+        error::BlockValidationError,
+        tests::{build_block, genesis, test_spec},
+    };
+
+    use super::*;
+
+    fn sender() -> Address {
+        Address::from_hex("a24a188cdcb3bf5fc6ec498d2657c6066b242028".to_string()).unwrap()
+    }
+
+    fn transfer(nonce: u64, value: u64) -> Transaction {
+        Transaction::Pint(PintTx {
+            chain_id: 0,
+            nonce,
+            to: Address::from_byte([7; 20]),
+            fee: 1,
+            value: U256::from(value),
+        })
+    }
+
+    #[test]
+    fn test_simulate_transaction() {
+        let db = InMemoryDB::new();
+        init_genesis(&db, &genesis()).unwrap();
+        let provider = PintStateProviderFactory::new(db.clone());
+
+        let outcome = simulate_transaction(
+            provider.latest().unwrap(),
+            test_spec(),
+            transfer(0, 3),
+            sender(),
+        )
+        .unwrap();
+        assert!(outcome.receipt.success);
+        assert_eq!(outcome.receipt.fee, U256::from(1));
+        assert_eq!(outcome.state_diff.len(), 2);
+        assert_eq!(outcome.state_diff[&sender()].balance, U256::from(6));
+        assert_eq!(outcome.state_diff[&sender()].nonce, 1);
+        assert_eq!(
+            outcome.state_diff[&Address::from_byte([7; 20])].balance,
+            U256::from(3)
+        );
+
+        // the database is untouched
+        assert_eq!(db.block_number(), 0);
+        assert_eq!(
+            db.basic(&sender()).unwrap().unwrap().balance,
+            U256::from(10)
+        );
+
+        let err = simulate_transaction(
+            provider.latest().unwrap(),
+            test_spec(),
+            transfer(0, 100),
+            sender(),
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            BlockExecutionError::Validation(BlockValidationError::InsufficientBalance { .. })
+        ));
+    }
+
+    #[test]
+    fn test_simulate_at_past_block() {
+        let db = InMemoryDB::new();
+        init_genesis(&db, &genesis()).unwrap();
+        db.commit_block(build_block(&db, test_spec()).into())
+            .unwrap();
+        let provider = PintStateProviderFactory::new(db.clone());
+
+        // the sender used nonce 0 in block 1
+        assert!(matches!(
+            simulate_transaction(
+                provider.latest().unwrap(),
+                test_spec(),
+                transfer(0, 1),
+                sender(),
+            ),
+            Err(BlockExecutionError::Validation(
+                BlockValidationError::NonceMismatch {
+                    expected: 1,
+                    got: 0
+                }
+            ))
+        ));
+        let outcome = simulate_transaction(
+            provider.state_by_block_number(0).unwrap(),
+            test_spec(),
+            transfer(0, 1),
+            sender(),
+        )
+        .unwrap();
+        assert_eq!(outcome.state_diff[&sender()].balance, U256::from(8));
+    }
+}
@@ -0,0 +1,117 @@
+//! Chain parameters the executor enforces on every block
+use primitives::types::{ChainId, U256};
+
+/// Parameters shared by all blocks of the chain
+#[derive(Debug, Clone, Default)]
+pub struct ChainSpec {
+    // transactions signed for another chain are rejected
+    pub chain_id: ChainId,
+    // percent of the fees that is burned instead of paid to the beneficiary, at most 100
+    pub fee_burn_percent: u8,
+    // subsidy of the first blocks, paid to the beneficiary on top of the fees
+    pub block_reward: U256,
+    // number of blocks after which the subsidy halves, it never halves if 0
+    pub reward_halving_interval: u64,
+    // most transactions a block may include, unlimited if None
+    pub max_block_transactions: Option<usize>,
+}
+
+impl ChainSpec {
+    pub fn with_chain_id(mut self, chain_id: ChainId) -> Self {
+        self.chain_id = chain_id;
+        self
+    }
+
+    pub fn with_fee_burn_percent(mut self, percent: u8) -> Self {
+        self.fee_burn_percent = percent.min(100);
+        self
+    }
+
+    pub fn with_block_reward(mut self, reward: U256, halving_interval: u64) -> Self {
+        self.block_reward = reward;
+        self.reward_halving_interval = halving_interval;
+        self
+    }
+
+    pub fn with_max_block_transactions(mut self, limit: usize) -> Self {
+        self.max_block_transactions = Some(limit);
+        self
+    }
+
+    /// Part of the fees of a block that is burned. The rest goes to the beneficiary.
+    pub fn burned_fees(&self, fees: U256) -> U256 {
+        fees * U256::from(self.fee_burn_percent.min(100)) / U256::from(100)
+    }
+
+    /// Subsidy of the block. The genesis block has none.
+    pub fn block_reward(&self, number: u64) -> U256 {
+        if number == 0 {
+            return U256::ZERO;
+        }
+        let halvings = match self.reward_halving_interval {
+            0 => 0,
+            interval => (number - 1) / interval,
+        };
+        if halvings >= 256 {
+            return U256::ZERO;
+        }
+        self.block_reward >> halvings as usize
+    }
+
+    /// Supply created by the rewards of the blocks up to `number`, on top of the genesis
+    /// allocations
+    pub fn issued_supply(&self, number: u64) -> U256 {
+        if self.reward_halving_interval == 0 {
+            return self.block_reward * U256::from(number);
+        }
+
+        // every period between two halvings pays the same reward per block
+        let mut issued = U256::ZERO;
+        let mut start = 1;
+        while start <= number {
+            let reward = self.block_reward(start);
+            if reward.is_zero() {
+                break;
+            }
+            let end = number.min(start.saturating_add(self.reward_halving_interval - 1));
+            issued += reward * U256::from(end - start + 1);
+            if end == number {
+                break;
+            }
+            start = end + 1;
+        }
+        issued
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_block_reward_halves() {
+        let spec = ChainSpec::default().with_block_reward(U256::from(100), 10);
+        assert_eq!(spec.block_reward(0), U256::ZERO);
+        assert_eq!(spec.block_reward(1), U256::from(100));
+        assert_eq!(spec.block_reward(10), U256::from(100));
+        assert_eq!(spec.block_reward(11), U256::from(50));
+        assert_eq!(spec.block_reward(31), U256::from(12));
+        assert_eq!(spec.block_reward(u64::MAX), U256::ZERO);
+
+        let constant = ChainSpec::default().with_block_reward(U256::from(7), 0);
+        assert_eq!(constant.block_reward(u64::MAX), U256::from(7));
+        assert_eq!(constant.issued_supply(3), U256::from(21));
+    }
+
+    #[test]
+    fn test_issued_supply_sums_block_rewards() {
+        let spec = ChainSpec::default().with_block_reward(U256::from(100), 10);
+        let mut issued = U256::ZERO;
+        for number in 0..100 {
+            issued += spec.block_reward(number);
+            assert_eq!(spec.issued_supply(number), issued);
+        }
+        // the reward is halved away after 7 periods
+        assert_eq!(spec.issued_supply(u64::MAX), spec.issued_supply(70));
+    }
+}
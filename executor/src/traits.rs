@@ -1,12 +1,11 @@
-use primitives::block::traits::Block;
-
+use transaction::TransactionSigned;
 use transaction_pool::traits::PoolTransaction;
 
 use crate::{BlockBuilderOutcome, error::BlockExecutionError};
 
 /// Executable Tx Trait
 pub trait ExecutableTx {
-    fn from_pool_transaction<Tx: PoolTransaction>(tx: Tx) -> Self;
+    fn from_pool_transaction<Tx: PoolTransaction<Pooled = TransactionSigned>>(tx: Tx) -> Self;
 }
 
 /// BlockExecutor Trait
@@ -22,5 +21,5 @@ pub trait BlockExecutor {
         tx: &Self::Transaction,
     ) -> Result<Option<u64>, BlockExecutionError>;
 
-    fn finish<B: Block>(&self) -> Result<BlockBuilderOutcome<B>, BlockExecutionError>;
+    fn finish(self) -> Result<BlockBuilderOutcome, BlockExecutionError>;
 }
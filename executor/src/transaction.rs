@@ -1,7 +1,15 @@
 use std::sync::Arc;
 
-use primitives::types::Address;
-use transaction::{ChainId, TransactionSigned, U256, signed::Recovered, traits::Transaction};
+use primitives::{
+    signature::Signature,
+    types::{Address, TxHash},
+};
+use transaction::{
+    ChainId, TransactionSigned, U256,
+    signed::{Recovered, Signed},
+    traits::{SignableTransaction, Transaction},
+    transaction::{Transaction as UnsignedTransaction, TxEnvelope},
+};
 use transaction_pool::{traits::PoolTransaction, validate::ValidPoolTransaction};
 
 use crate::traits::ExecutableTx;
@@ -10,11 +18,15 @@ use crate::traits::ExecutableTx;
 #[derive(Debug)]
 pub struct ExecutableTranasction {
     pub tx_type: u8,
+    pub hash: TxHash,
     pub chain_id: ChainId,
     pub sender: Address,
     pub receiver: Address,
     pub nonce: u64,
     pub value: U256,
+    pub fee: U256,
+    // signed transaction that goes into the block body
+    pub transaction: TransactionSigned,
 }
 
 impl Transaction for ExecutableTranasction {
@@ -40,14 +52,17 @@ impl Transaction for ExecutableTranasction {
 }
 
 impl ExecutableTx for ExecutableTranasction {
-    fn from_pool_transaction<Tx: PoolTransaction>(tx: Tx) -> Self {
+    fn from_pool_transaction<Tx: PoolTransaction<Pooled = TransactionSigned>>(tx: Tx) -> Self {
         Self {
             tx_type: 0,
+            hash: tx.hash(),
             chain_id: tx.chain_id(),
             sender: tx.sender(),
             receiver: tx.to(),
             nonce: tx.nonce(),
             value: tx.value(),
+            fee: tx.cost(),
+            transaction: tx.clone_into_consensus().inner,
         }
     }
 }
@@ -60,27 +75,58 @@ impl From<Recovered<TransactionSigned>> for ExecutableTranasction {
         } = recovered;
         ExecutableTranasction {
             tx_type: tx.tx_type(),
+            hash: tx.hash(),
             chain_id: tx.chain_id(),
             sender: address,
             receiver: tx.to(),
             nonce: tx.nonce(),
             value: tx.value(),
+            fee: tx.cost(),
+            transaction: tx,
         }
     }
 }
 
 impl<T> From<Arc<ValidPoolTransaction<T>>> for ExecutableTranasction
 where
-    T: PoolTransaction,
+    T: PoolTransaction<Pooled = TransactionSigned>,
 {
     fn from(tx: Arc<ValidPoolTransaction<T>>) -> Self {
         ExecutableTranasction {
             tx_type: tx.transaction.tx_type(),
+            hash: tx.transaction.hash(),
             chain_id: tx.transaction.chain_id(),
             sender: tx.transaction.sender(),
             receiver: tx.transaction.to(),
             nonce: tx.transaction.nonce(),
             value: tx.transaction.value(),
+            fee: tx.transaction.cost(),
+            transaction: tx.transaction.clone_into_consensus().inner,
+        }
+    }
+}
+
+impl ExecutableTranasction {
+    /// Transaction the claimed sender would sign, carrying an empty signature.
+    /// Its hash is not the hash the signed transaction will have.
+    pub fn from_unsigned(tx: UnsignedTransaction, sender: Address) -> Self {
+        let signature = Signature::from_bytes_and_parity(&[0; 64], false);
+        let transaction = match tx {
+            UnsignedTransaction::Pint(tx) => {
+                let hash = tx.encode_for_signing();
+                TxEnvelope::Pint(Signed::new(tx, signature, hash))
+            }
+        };
+        Self {
+            tx_type: transaction.tx_type(),
+            hash: transaction.hash(),
+            chain_id: transaction.chain_id(),
+            sender,
+            receiver: transaction.to(),
+            nonce: transaction.nonce(),
+            value: transaction.value(),
+            fee: transaction.cost(),
+            transaction,
         }
     }
 }
@@ -1,6 +1,6 @@
-use std::{marker::PhantomData, net::IpAddr, sync::Arc};
+use std::{marker::PhantomData, net::IpAddr, path::PathBuf};
 
-use storage::{db::InMemoryDB, PintStateProviderFactory};
+use storage::{cache::CachedStateProviderFactory, genesis::{init_genesis, Genesis}, prune::{PruneMode, PruningDB}, wal::WalDB, PintStateProviderFactory};
 
 use crate::{components::{
         consensus::ConsensusBuilder, execute::ExecutorBuilder, network::NetworkBuilder, payload::PayloadServiceBuilder, pool::PoolBuilder, FullNodeTypes, NodeComponentsBuilder
@@ -85,19 +85,26 @@ where
 pub struct LaunchContext<CB> {
     pub address: IpAddr,
     pub port: u16,
+    // write-ahead log of the database, created on the first launch
+    pub db_path: PathBuf,
+    // history kept by the database, pruned in the background after every commit
+    pub prune_mode: PruneMode,
+    pub genesis: Genesis,
     pub components_builder: CB,
 }
 
-impl<CB> LaunchContext<CB> where CB: NodeComponentsBuilder<Provider = PintStateProviderFactory<Arc<InMemoryDB>>> {
+impl<CB> LaunchContext<CB> where CB: NodeComponentsBuilder<Provider = CachedStateProviderFactory<PintStateProviderFactory<PruningDB<WalDB>>>> {
     pub async fn launch(self) -> Result<(), LaunchError>{
         // Here you would implement the logic to launch the node using the provided components builder.
         // This is a placeholder for the actual launch logic.
         println!("Launching node at {}:{}", self.address, self.port);
 
-        // making database
-        let database = Arc::new(InMemoryDB::new());
+        // opening database, replaying the chain of earlier launches
+        let database = PruningDB::new(WalDB::open(&self.db_path)?, self.prune_mode);
+        // writes block 0, or fails if the database belongs to another chain
+        init_genesis(&database, &self.genesis)?;
         // making providerFactory
-        let provider = PintStateProviderFactory::new(database);
+        let provider = CachedStateProviderFactory::new(PintStateProviderFactory::new(database));
         // build_components
         let components = self.components_builder.build_components(provider).await?;
 
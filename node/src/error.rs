@@ -1,13 +1,33 @@
+use storage::error::DatabaseError;
+
 pub enum BuildError {
     PoolError,
 }
 #[derive(Debug)]
 pub enum LaunchError {
     BuildError,
+    Database(DatabaseError),
 }
 
 impl From<BuildError> for LaunchError {
     fn from(value: BuildError) -> Self {
         Self::BuildError
     }
-}
\ No newline at end of file
+}
+
+impl From<DatabaseError> for LaunchError {
+    fn from(value: DatabaseError) -> Self {
+        Self::Database(value)
+    }
+}
+
+impl std::fmt::Display for LaunchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LaunchError::BuildError => write!(f, "Failed to build node components"),
+            LaunchError::Database(e) => write!(f, "Failed to initialize the database: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for LaunchError {}
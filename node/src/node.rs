@@ -4,7 +4,7 @@ use consensus::PintConsensus;
 use executor::PintBlockExecutor;
 use net::PintNetworkHandle;
 use payload::{builder::PayloadBuilderHandle, traits::PayloadTypes, PintPayloadTypes};
-use storage::{db::{Database, InMemoryDB}, PintStateProviderFactory};
+use storage::{cache::CachedStateProviderFactory, db::{Database, InMemoryDB}, prune::PruningDB, wal::WalDB, PintStateProviderFactory};
 use transaction_pool::{
     config::PoolConfig, ordering::PintOrdering, traits::{PintPooledTransaction, TransactionPool}, validate::{pint::{PintTransactionValidator, PintTransactionValidatorBuilder}, task::TransactionValidationTaskExecutor}, Pool
 };
@@ -43,7 +43,8 @@ impl PintNode {
 
 
 impl FullNodeTypes for PintNode {
-    type Provider = PintStateProviderFactory<Arc<InMemoryDB>>;
+    // One cache is shared by the pool, payload builder and RPC through clones of the provider
+    type Provider = CachedStateProviderFactory<PintStateProviderFactory<PruningDB<WalDB>>>;
     type Payload = PintPayloadTypes;
 }
 
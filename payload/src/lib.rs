@@ -5,14 +5,15 @@ use std::{
 };
 
 use executor::{
-    BlockBuilderOutcome, PintBlockExecutor,
+    BlockBuilderOutcome, BlockEnv, PintBlockExecutor,
     database::State,
-    error::{BlockExecutionError, BlockValidationError},
+    error::BlockExecutionError,
+    spec::ChainSpec,
     traits::BlockExecutor,
 };
 use primitives::{
     block::{Block, body::SealedBlock},
-    types::{BlockHash, PayloadId, U256},
+    types::{Address, BlockHash, PayloadId, U256},
 };
 
 use storage::traits::{BlockReader, StateProviderFactory};
@@ -129,18 +130,19 @@ where
 pub struct PintPayloadBuilder<Pool, Client> {
     client: Client,
     pool: Pool,
+    spec: Arc<ChainSpec>,
 }
 
 impl<Pool, Client> PintPayloadBuilder<Pool, Client> {
-    pub const fn new(client: Client, pool: Pool) -> Self {
-        Self { client, pool }
+    pub const fn new(client: Client, pool: Pool, spec: Arc<ChainSpec>) -> Self {
+        Self { client, pool, spec }
     }
 }
 
 impl<Pool, Client> PayloadBuilder for PintPayloadBuilder<Pool, Client>
 where
     Client: StateProviderFactory + Clone,
-    Pool: TransactionPool,
+    Pool: TransactionPool<Transaction: PoolTransaction<Pooled = TransactionSigned>>,
 {
     type BuiltPayload = PintBuiltPayload;
     type Attributes = PintPayloadBuilderAttributes;
@@ -149,7 +151,7 @@ where
         &self,
         args: BuildArguments<Self::Attributes>,
     ) -> Result<BuildOutcome<PintBuiltPayload>, PayloadBuilderError> {
-        default_pint_payload(args, self.client.clone(), self.pool.clone())
+        default_pint_payload(args, self.client.clone(), self.pool.clone(), self.spec.clone())
     }
 }
 
@@ -160,6 +162,8 @@ pub struct PintPayloadBuilderAttributes {
     // Parent block to build to payload on top
     pub parent: BlockHash,
     pub timestamp: u64,
+    // Account the fees and the reward of the block are paid to
+    pub beneficiary: Address,
     pub parent_beacon_block_root: Option<BlockHash>,
 }
 
@@ -181,7 +185,10 @@ impl PayloadBuilderAttributes for PintPayloadBuilderAttributes {
 pub struct PintBuiltPayload {
     pub id: PayloadId,
     pub block: Arc<SealedBlock<Block<TransactionSigned>>>,
+    // fees credited to the beneficiary, after the burn
     pub fees: U256,
+    // what the beneficiary receives: the fees and the block reward
+    pub value: U256,
 }
 
 impl PintBuiltPayload {
@@ -189,8 +196,14 @@ impl PintBuiltPayload {
         id: PayloadId,
         block: Arc<SealedBlock<Block<TransactionSigned>>>,
         fees: U256,
+        value: U256,
     ) -> Self {
-        Self { id, block, fees }
+        Self {
+            id,
+            block,
+            fees,
+            value,
+        }
     }
 }
 
@@ -205,9 +218,10 @@ pub fn default_pint_payload<Client, Pool>(
     args: BuildArguments<PintPayloadBuilderAttributes>,
     client: Client,
     pool: Pool,
+    spec: Arc<ChainSpec>,
 ) -> Result<BuildOutcome<PintBuiltPayload>, PayloadBuilderError>
 where
-    Pool: TransactionPool,
+    Pool: TransactionPool<Transaction: PoolTransaction<Pooled = TransactionSigned>>,
     Client: StateProviderFactory + Clone,
 {
     let BuildArguments {
@@ -216,31 +230,42 @@ where
     } = args;
     let state_provider = client.state_by_block_hash(parent_header.hash())?;
     let state = State::new(state_provider);
-    let mut executor = PintBlockExecutor {
-        state,
-        receipts: Vec::new(),
-    };
+    let env = BlockEnv::new(&parent_header, attributes.timestamp, attributes.beneficiary);
+    let mut executor = PintBlockExecutor::new(state, spec, env);
+    executor.prepare_execute()?;
     let mut best_txs = pool.best_transactions();
-    let mut total_fee = U256::ZERO;
 
     while let Some(pool_tx) = best_txs.next() {
+        let checkpoint = executor.checkpoint()?;
         match executor.execute_transaction(&pool_tx.clone().into()) {
             Ok(_) => {}
-            Err(BlockExecutionError::Validation(BlockValidationError::InvalidTx)) => {}
-            Err(err) => return Err(PayloadBuilderError::ExecutionError),
+            // invalid transactions are left out of the block
+            Err(BlockExecutionError::Validation(_)) => {
+                executor.revert_to(checkpoint)?;
+                continue;
+            }
+            Err(_) => return Err(PayloadBuilderError::ExecutionError),
         };
 
-        let miner_fee = pool_tx.transaction.cost();
-        total_fee += miner_fee;
+        // the block is full, so the transaction is rolled back and waits for the next block
+        if executor.exceeds_block_limit() {
+            executor.revert_to(checkpoint)?;
+            break;
+        }
+        executor.commit(checkpoint)?;
     }
 
+    // fees are already after the burn, the beneficiary receives them and the reward
     let BlockBuilderOutcome {
-        receipts, block, ..
+        block,
+        fees,
+        reward,
+        ..
     } = executor.finish()?;
 
     let sealed_block = Arc::new(block);
 
-    let payload = PintBuiltPayload::new(attributes.id, sealed_block, total_fee);
+    let payload = PintBuiltPayload::new(attributes.id, sealed_block, fees, fees + reward);
     Ok(BuildOutcome::Better { payload })
 }
 
@@ -250,4 +275,84 @@ pub struct PintPayloadTypes;
 impl PayloadTypes for PintPayloadTypes {
     type BuiltPayload = PintBuiltPayload;
     type PayloadBuilderAttributes = PintPayloadBuilderAttributes;
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use storage::{
+        PintStateProviderFactory,
+        db::InMemoryDB,
+        genesis::{Genesis, GenesisAccount, init_genesis},
+    };
+    use transaction::{
+        traits::{Decodable, SignedTransaction},
+        transaction::TxEnvelope,
+    };
+    use transaction_pool::{
+        Pool, config::PoolConfig, ordering::PintOrdering, traits::PintPooledTransaction,
+        validate::pint::PintTransactionValidatorBuilder,
+    };
+
+    use super::*;
+
+    // Both senders are funded in the genesis block, and both transactions pay a fee of 1.
+    const SENDERS: [&str; 2] = [
+        "a24a188cdcb3bf5fc6ec498d2657c6066b242028",
+        "314f3ea92a6fc23d6b66057d3acfba04d6b08b58",
+    ];
+    const TRANSACTIONS: [&str; 2] = [
+        "0000000000000000000000000000000000e0aa4e80c739ee08b5a6680586d1bf3991840c21000000000000000000000000000000010000000000000000000000000000000000000000000000000000000000000001be2855167f254060b5812e4a2849c7ba3d34ea4aeb175e87f83c2a7c1424379a6e722511c17cb5191e090b2a75dfe2b924d2b1bcbf0a2f26e207cb728dcaa34501",
+        "0000000000000000000000000000000000802d9a22dddb7b03ff11eea121bdd4a75135e4080000000000000000000000000000000100000000000000000000000000000000000000000000000000000000000000016969fda9b07fdf03f3092c06e9bd4def87edd1138b214be3ab724d980c0c12764b7150e282c63b1f42107b07a82a946d15ff56d921c2acd6fab423e22b94485f01",
+    ];
+
+    #[tokio::test]
+    async fn test_payload_value_is_the_payout() {
+        let genesis = SENDERS.iter().fold(Genesis::default(), |genesis, sender| {
+            let account = GenesisAccount {
+                balance: U256::from(10),
+                ..Default::default()
+            };
+            genesis.with_account(Address::from_hex(sender.to_string()).unwrap(), account)
+        });
+        let db = InMemoryDB::new();
+        init_genesis(&db, &genesis).unwrap();
+        let client = PintStateProviderFactory::new(db.clone());
+
+        let validator = PintTransactionValidatorBuilder::new(client.clone()).build();
+        let pool = Pool::new(validator, PintOrdering::default(), PoolConfig::default());
+        for raw in TRANSACTIONS {
+            let (tx, _) = TxEnvelope::decode(&hex::decode(raw).unwrap()).unwrap();
+            let tx = PintPooledTransaction::from_pooled(tx.try_into_recovered().unwrap());
+            pool.add_external_transaction(tx).await.unwrap();
+        }
+
+        let spec = ChainSpec::default()
+            .with_fee_burn_percent(50)
+            .with_block_reward(U256::from(1_000), 0);
+        let beneficiary = Address::from_byte([0xbe; 20]);
+        let args = BuildArguments {
+            parent_header: Arc::new(genesis.header()),
+            attributes: PintPayloadBuilderAttributes {
+                id: PayloadId::new([1; 8]),
+                parent: genesis.header().hash(),
+                timestamp: 1,
+                beneficiary: beneficiary.clone(),
+                parent_beacon_block_root: None,
+            },
+        };
+        let Ok(BuildOutcome::Better { payload }) =
+            default_pint_payload(args, client, pool, Arc::new(spec))
+        else {
+            panic!("the payload was not built");
+        };
+
+        // one of the two fees is burned
+        assert_eq!(payload.fees, U256::from(1));
+        assert_eq!(payload.value, U256::from(1 + 1_000));
+        assert_eq!(payload.block.body().transaction.len(), 2);
+        assert_eq!(
+            payload.block.sealed_header().header().beneficiary,
+            beneficiary
+        );
+    }
+}
@@ -0,0 +1,55 @@
+//! `pint db` commands
+use std::path::PathBuf;
+
+use clap::Subcommand;
+use storage::{
+    check::{check_database, repair},
+    wal::WalDB,
+};
+
+#[derive(Subcommand, Debug)]
+pub enum DbCommand {
+    /// Verify the stored chain and report every inconsistency
+    Check {
+        /// Database log to check
+        #[arg(short, long)]
+        path: PathBuf,
+
+        /// Rebuild corrupted indices, the log is only written with this flag
+        #[arg(long)]
+        repair: bool,
+    },
+}
+
+impl DbCommand {
+    pub fn execute(self) -> eyre::Result<()> {
+        match self {
+            DbCommand::Check { path, repair: fix } => {
+                // Nothing is written to the log unless a repair is requested.
+                let mut report = check_database(&WalDB::open_read_only(&path)?)?;
+                println!(
+                    "Checked blocks {} to {} of {}",
+                    report.first_block,
+                    report.last_block,
+                    path.display()
+                );
+                for finding in &report.findings {
+                    let kind = if finding.is_repairable() { "repairable" } else { "corrupt" };
+                    println!("{}: {}", kind, finding);
+                }
+
+                if fix && !report.is_ok() {
+                    let database = WalDB::open(&path)?;
+                    let changed = repair(&database, &report)?;
+                    println!("Rebuilt {} index entries", changed);
+                    report = check_database(&database)?;
+                }
+                if !report.is_ok() {
+                    eyre::bail!("{} findings remain", report.findings.len());
+                }
+                println!("No findings");
+            }
+        }
+        Ok(())
+    }
+}
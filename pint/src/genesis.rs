@@ -0,0 +1,71 @@
+//! Genesis spec file of `pint --genesis`
+//!
+//! ```json
+//! {
+//!   "timestamp": 0,
+//!   "alloc": {
+//!     "0xa24a188cdcb3bf5fc6ec498d2657c6066b242028": {
+//!       "balance": "1000",
+//!       "nonce": 0,
+//!       "storage": { "0x01": "1" }
+//!     }
+//!   }
+//! }
+//! ```
+use std::{fs, path::Path};
+
+use eyre::{OptionExt, WrapErr};
+use primitives::types::{Address, StorageKey, U256};
+use serde_json::Value;
+use storage::genesis::{Genesis, GenesisAccount};
+
+/// Reads the genesis spec the node is started with
+pub fn load_genesis(path: &Path) -> eyre::Result<Genesis> {
+    let json: Value = serde_json::from_slice(&fs::read(path)?)
+        .wrap_err_with(|| format!("invalid genesis file {}", path.display()))?;
+
+    let mut genesis = Genesis::default().with_timestamp(json["timestamp"].as_u64().unwrap_or(0));
+    if let Some(alloc) = json.get("alloc") {
+        let alloc = alloc.as_object().ok_or_eyre("alloc must be an object")?;
+        for (address, account) in alloc {
+            let address = Address::from_hex(address.trim_start_matches("0x").to_string())
+                .map_err(|e| eyre::eyre!("invalid address {}: {:?}", address, e))?;
+            genesis = genesis.with_account(address, parse_account(account)?);
+        }
+    }
+    Ok(genesis)
+}
+
+fn parse_account(json: &Value) -> eyre::Result<GenesisAccount> {
+    let mut account = GenesisAccount {
+        nonce: json["nonce"].as_u64().unwrap_or(0),
+        balance: parse_u256(&json["balance"])?,
+        ..Default::default()
+    };
+    if let Some(storage) = json.get("storage") {
+        let storage = storage.as_object().ok_or_eyre("storage must be an object")?;
+        for (key, value) in storage {
+            let key = U256::from_str_radix(key.trim_start_matches("0x"), 16)
+                .wrap_err_with(|| format!("invalid storage key {}", key))?;
+            account
+                .storage
+                .insert(StorageKey::from(key), parse_u256(value)?);
+        }
+    }
+    Ok(account)
+}
+
+// Decimal or 0x-prefixed hex string, or a number. Missing values are zero.
+fn parse_u256(json: &Value) -> eyre::Result<U256> {
+    match json {
+        Value::Null => Ok(U256::ZERO),
+        Value::Number(number) => number
+            .as_u64()
+            .map(U256::from)
+            .ok_or_eyre("amounts must be unsigned integers"),
+        Value::String(value) => value
+            .parse()
+            .wrap_err_with(|| format!("invalid amount {}", value)),
+        other => eyre::bail!("invalid amount {}", other),
+    }
+}
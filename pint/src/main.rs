@@ -1,21 +1,54 @@
-use clap::Parser;
-use node::{builder::LaunchContext, error::LaunchError, node::PintNode};
-use std::net::IpAddr;
+use clap::{Parser, Subcommand};
+use node::{builder::LaunchContext, node::PintNode};
+use std::{net::IpAddr, path::PathBuf};
+use storage::{genesis::Genesis, prune::PruneMode};
+
+use crate::{db::DbCommand, genesis::load_genesis, snapshot::SnapshotCommand};
+
+mod db;
+mod genesis;
+mod snapshot;
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Args {
     /// Address
-    #[arg(short, long)]
-    address: IpAddr,
+    #[arg(short, long, required = true)]
+    address: Option<IpAddr>,
 
     /// Port
     #[arg(short, long, default_value_t = 8557)]
     port: u16,
+
+    /// Database log, created on the first start
+    #[arg(long, default_value = "pint.wal")]
+    db: PathBuf,
+
+    /// History to keep: archive, full:<blocks> or minimal
+    #[arg(long, default_value = "archive")]
+    prune: PruneMode,
+
+    /// Genesis spec file, an empty development chain if not set
+    #[arg(long)]
+    genesis: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Option<Commands>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Commands {
+    /// State snapshot tools
+    #[command(subcommand)]
+    Snapshot(SnapshotCommand),
+    /// Database tools
+    #[command(subcommand)]
+    Db(DbCommand),
 }
 
 #[tokio::main]
-async fn main() -> Result<(), LaunchError>{
+async fn main() -> eyre::Result<()> {
 
     // Enable backtraces unless a RUST_BACKTRACE value has already been explicitly provided.
     if std::env::var_os("RUST_BACKTRACE").is_none() {
@@ -23,13 +56,27 @@ async fn main() -> Result<(), LaunchError>{
     }
     
     let args = Args::parse();
-    let address = args.address;
+
+    match args.command {
+        Some(Commands::Snapshot(command)) => {
+            command.execute()?;
+            return Ok(());
+        }
+        Some(Commands::Db(command)) => return command.execute(),
+        None => {}
+    }
+
+    let address = args.address.expect("required without a subcommand");
     let port = args.port;
+    let genesis = match &args.genesis {
+        Some(path) => load_genesis(path)?,
+        None => Genesis::default(),
+    };
 
     // Ensure DB is imported from the appropriate module
     let components_builder = PintNode::components::<PintNode>();
-    let ctx = LaunchContext { address, port, components_builder };
+    let ctx = LaunchContext { address, port, db_path: args.db, prune_mode: args.prune, genesis, components_builder };
 
     ctx.launch().await?;
     Ok(())
-}
\ No newline at end of file
+}
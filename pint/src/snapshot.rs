@@ -0,0 +1,94 @@
+//! `pint snapshot` commands
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::PathBuf,
+};
+
+use clap::Subcommand;
+use storage::{
+    db::Database,
+    error::SnapshotError,
+    snapshot::{DEFAULT_CHUNK_SIZE, SnapshotExporter, SnapshotImporter},
+    wal::WalDB,
+};
+
+#[derive(Subcommand, Debug)]
+pub enum SnapshotCommand {
+    /// Write the state at a block into a snapshot file
+    Export {
+        /// Database log to read the state from, it is not written to
+        #[arg(short, long)]
+        path: PathBuf,
+
+        /// Block number, the latest block if not set
+        #[arg(short, long)]
+        block: Option<u64>,
+
+        /// Snapshot file to write
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Accounts per chunk
+        #[arg(long, default_value_t = DEFAULT_CHUNK_SIZE)]
+        chunk_size: usize,
+    },
+    /// Verify a snapshot file and seed a fresh database with it
+    Import {
+        /// Database log to seed, created if it does not exist
+        #[arg(short, long)]
+        path: PathBuf,
+
+        /// Snapshot file to read
+        #[arg(short, long)]
+        input: PathBuf,
+    },
+}
+
+impl SnapshotCommand {
+    pub fn execute(self) -> Result<(), SnapshotError> {
+        match self {
+            SnapshotCommand::Export {
+                path,
+                block,
+                output,
+                chunk_size,
+            } => {
+                let database = WalDB::open_read_only(&path)?;
+                let number = block.unwrap_or_else(|| database.block_number());
+                let mut writer = BufWriter::new(File::create(&output)?);
+                let snapshot = match SnapshotExporter::new(database)
+                    .with_chunk_size(chunk_size)
+                    .export(number, &mut writer)
+                {
+                    Ok(snapshot) => snapshot,
+                    Err(err) => {
+                        // don't leave a broken snapshot behind
+                        drop(writer);
+                        let _ = std::fs::remove_file(&output);
+                        return Err(err);
+                    }
+                };
+                println!(
+                    "Exported {} accounts of block {} ({}) into {}",
+                    snapshot.account_count,
+                    number,
+                    snapshot.header.hash(),
+                    output.display()
+                );
+            }
+            SnapshotCommand::Import { path, input } => {
+                let database = WalDB::open(&path)?;
+                let mut reader = BufReader::new(File::open(&input)?);
+                let snapshot = SnapshotImporter::new(database).import(&mut reader)?;
+                println!(
+                    "Imported {} accounts at block {} ({})",
+                    snapshot.account_count,
+                    snapshot.header.number(),
+                    snapshot.header.hash()
+                );
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,7 +1,15 @@
 //! Implements [Account]
+use std::collections::BTreeMap;
+
 use alloy_primitives::U256;
 
-#[derive(Debug, Default, Copy, Clone)]
+use crate::types::{StorageKey, StorageValue};
+
+/// Storage slots of a single account ordered by key.
+/// A zero value is the same as an empty slot, so it is never stored.
+pub type AccountStorage = BTreeMap<StorageKey, StorageValue>;
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
 /// On chain accout
 pub struct Account {
     pub nonce: u64,
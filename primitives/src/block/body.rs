@@ -1,14 +1,40 @@
 use crate::block::{header::SealedHeader, traits::Block};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct BlockBody<T> {
     pub transaction: Vec<T>,
 }
 
+impl<T> Default for BlockBody<T> {
+    fn default() -> Self {
+        Self {
+            transaction: Vec::new(),
+        }
+    }
+}
+
 impl<T> crate::block::traits::BlockBody for BlockBody<T> {}
 
-#[derive(Clone)]
+#[derive(Debug, Clone)]
 pub struct SealedBlock<B: Block> {
     header: SealedHeader<B::Header>,
     body: B::Body,
 }
+
+impl<B: Block> SealedBlock<B> {
+    pub fn new(header: SealedHeader<B::Header>, body: B::Body) -> Self {
+        Self { header, body }
+    }
+
+    pub const fn sealed_header(&self) -> &SealedHeader<B::Header> {
+        &self.header
+    }
+
+    pub const fn body(&self) -> &B::Body {
+        &self.body
+    }
+
+    pub fn split(self) -> (SealedHeader<B::Header>, B::Body) {
+        (self.header, self.body)
+    }
+}
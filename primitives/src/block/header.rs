@@ -1,40 +1,76 @@
 use std::sync::OnceLock;
 
-use crate::types::{BlockHash, TxHash};
+use crate::types::{Address, BlockHash, TxHash};
 use alloy_primitives::B256;
 use k256::sha2::{Digest, Sha256};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct Header {
     pub previous_hash: TxHash,
+    pub number: u64,
     pub transaction_root: B256,
     pub state_root: B256,
+    pub receipts_root: B256,
     pub timestamp: u64,
+    /// Account credited with the fees and the block reward
+    pub beneficiary: Address,
 }
 
 impl Header {
     pub fn hash_slow(&self) -> BlockHash {
         let mut hasher = Sha256::new();
         hasher.update(self.previous_hash.to_string().as_bytes());
+        hasher.update(self.number.to_string().as_bytes());
         hasher.update(self.transaction_root.to_string().as_bytes());
         hasher.update(self.state_root.to_string().as_bytes());
+        hasher.update(self.receipts_root.to_string().as_bytes());
         hasher.update(self.timestamp.to_string().as_bytes());
+        hasher.update(self.beneficiary.get_addr_hex().as_bytes());
         B256::from_slice(&hasher.finalize())
     }
 }
 
 impl crate::block::traits::BlockHeader for Header {}
 
-// TODO: Implement SealedHeader
 // Runtime Memory Cache Structure for block header with block hash
-#[derive(Clone)]
+#[derive(Debug, Clone)]
 pub struct SealedHeader<H = Header> {
     hash: OnceLock<BlockHash>,
     header: H,
 }
 
+impl<H> SealedHeader<H> {
+    /// Seal the header with a known hash
+    pub fn new(header: H, hash: BlockHash) -> Self {
+        Self {
+            hash: OnceLock::from(hash),
+            header,
+        }
+    }
+
+    pub const fn header(&self) -> &H {
+        &self.header
+    }
+
+    pub fn into_header(self) -> H {
+        self.header
+    }
+}
+
 impl SealedHeader {
+    /// Seal the header, the hash is computed lazily
+    pub fn seal_slow(header: Header) -> Self {
+        Self {
+            hash: OnceLock::new(),
+            header,
+        }
+    }
+
     pub fn hash(&self) -> BlockHash {
         *self.hash.get_or_init(|| self.header.hash_slow())
     }
+
+    pub fn number(&self) -> u64 {
+        self.header.number
+    }
 }
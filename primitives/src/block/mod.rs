@@ -4,7 +4,7 @@ use crate::block::{body::BlockBody, header::Header};
 pub mod body;
 pub mod header;
 pub mod traits;
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Block<T, H = Header> {
     pub header: H,
     pub body: BlockBody<T>,
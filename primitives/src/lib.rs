@@ -10,4 +10,8 @@ pub mod account;
 
 pub mod block;
 
+pub mod proofs;
+
+pub mod receipt;
+
 pub mod signature;
@@ -0,0 +1,154 @@
+//! Roots committed to by the block header
+use std::collections::HashMap;
+
+use alloy_primitives::B256;
+use k256::sha2::{Digest, Sha256};
+
+use crate::{
+    account::{Account, AccountStorage},
+    receipt::Receipt,
+    types::{Address, StorageKey, StorageValue, TxHash},
+};
+
+/// Binary merkle root of the given leaves. The last leaf is paired with itself
+/// on odd levels, and an empty list has the zero root.
+pub fn merkle_root(leaves: impl IntoIterator<Item = B256>) -> B256 {
+    let mut level: Vec<B256> = leaves.into_iter().collect();
+    if level.is_empty() {
+        return B256::ZERO;
+    }
+
+    while level.len() > 1 {
+        level = level
+            .chunks(2)
+            .map(|pair| {
+                let mut hasher = Sha256::new();
+                hasher.update(pair[0]);
+                hasher.update(pair.get(1).unwrap_or(&pair[0]));
+                B256::from_slice(&hasher.finalize())
+            })
+            .collect();
+    }
+    level[0]
+}
+
+/// Transaction root of a block over the transaction hashes in block order
+pub fn calculate_transaction_root(tx_hashes: impl IntoIterator<Item = TxHash>) -> B256 {
+    merkle_root(tx_hashes)
+}
+
+/// Receipts root of a block
+pub fn calculate_receipts_root(receipts: &[Receipt]) -> B256 {
+    merkle_root(receipts.iter().map(Receipt::hash_slow))
+}
+
+/// Storage root of a single account over its non-zero slots ordered by key
+pub fn calculate_storage_root<'a>(
+    slots: impl IntoIterator<Item = (&'a StorageKey, &'a StorageValue)>,
+) -> B256 {
+    let mut slots: Vec<_> = slots
+        .into_iter()
+        .filter(|(_, value)| !value.is_zero())
+        .collect();
+    slots.sort_unstable_by_key(|(key, _)| *key);
+    merkle_root(slots.into_iter().map(|(key, value)| {
+        let mut hasher = Sha256::new();
+        hasher.update(key);
+        hasher.update(value.to_be_bytes::<32>());
+        B256::from_slice(&hasher.finalize())
+    }))
+}
+
+/// Leaf of the state root for a single account
+pub fn account_leaf(address: &Address, account: &Account, storage_root: B256) -> B256 {
+    let mut hasher = Sha256::new();
+    hasher.update(address.get_addr());
+    hasher.update(account.nonce.to_be_bytes());
+    hasher.update(account.balance.to_be_bytes::<32>());
+    hasher.update(storage_root);
+    B256::from_slice(&hasher.finalize())
+}
+
+/// State root over all accounts ordered by address.
+/// Accounts without an entry in `storage` have the empty storage root.
+pub fn calculate_state_root<'a>(
+    accounts: impl IntoIterator<Item = (&'a Address, &'a Account)>,
+    storage: &HashMap<Address, AccountStorage>,
+) -> B256 {
+    let mut accounts: Vec<_> = accounts.into_iter().collect();
+    accounts.sort_unstable_by_key(|(address, _)| *address);
+    merkle_root(accounts.into_iter().map(|(address, account)| {
+        let storage_root = storage
+            .get(address)
+            .map(calculate_storage_root)
+            .unwrap_or(B256::ZERO);
+        account_leaf(address, account, storage_root)
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_receipts_root() {
+        assert_eq!(calculate_receipts_root(&[]), B256::ZERO);
+
+        let receipt = Receipt {
+            success: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            calculate_receipts_root(std::slice::from_ref(&receipt)),
+            receipt.hash_slow()
+        );
+
+        let other = Receipt {
+            sender_nonce: 1,
+            ..receipt.clone()
+        };
+        let root = calculate_receipts_root(&[receipt.clone(), other.clone()]);
+        assert_ne!(root, calculate_receipts_root(&[other, receipt]));
+    }
+
+    #[test]
+    fn test_state_root_ignores_order() {
+        let a = (Address::random(), Account::default());
+        let b = (
+            Address::random(),
+            Account {
+                nonce: 1,
+                balance: crate::types::U256::from(7),
+            },
+        );
+        let no_storage = HashMap::new();
+        let root = calculate_state_root([(&a.0, &a.1), (&b.0, &b.1)], &no_storage);
+        assert_eq!(
+            root,
+            calculate_state_root([(&b.0, &b.1), (&a.0, &a.1)], &no_storage)
+        );
+        assert_ne!(root, calculate_state_root([(&a.0, &a.1)], &no_storage));
+    }
+
+    #[test]
+    fn test_storage_root_commits_to_slots() {
+        let key = StorageKey::random();
+        let slots = AccountStorage::from([(key, StorageValue::from(1))]);
+        let root = calculate_storage_root(&slots);
+        assert_ne!(root, B256::ZERO);
+
+        // zero slots are the same as empty ones
+        let mut with_zero = slots.clone();
+        with_zero.insert(StorageKey::random(), StorageValue::ZERO);
+        assert_eq!(calculate_storage_root(&with_zero), root);
+        assert_eq!(calculate_storage_root(&AccountStorage::new()), B256::ZERO);
+
+        let address = Address::random();
+        let account = Account::default();
+        let storage = HashMap::from([(address.clone(), slots)]);
+        assert_ne!(
+            calculate_state_root([(&address, &account)], &storage),
+            calculate_state_root([(&address, &account)], &HashMap::new())
+        );
+    }
+}
@@ -0,0 +1,33 @@
+//! Implements [Receipt]
+use alloy_primitives::B256;
+use k256::sha2::{Digest, Sha256};
+
+use crate::types::{TxHash, U256};
+
+/// Transaction Execution Result for BlockExecutor
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct Receipt {
+    pub tx_type: u8,
+    pub tx_hash: TxHash,
+    pub success: bool,
+    // fee paid by this transaction
+    pub fee: U256,
+    // sum of the fees paid by the transactions up to this one in the block
+    pub cumulative_fee: U256,
+    // nonces after the transaction was executed
+    pub sender_nonce: u64,
+    pub receiver_nonce: u64,
+}
+
+impl Receipt {
+    pub fn hash_slow(&self) -> B256 {
+        let mut hasher = Sha256::new();
+        hasher.update([self.tx_type, self.success as u8]);
+        hasher.update(self.tx_hash);
+        hasher.update(self.fee.to_be_bytes::<32>());
+        hasher.update(self.cumulative_fee.to_be_bytes::<32>());
+        hasher.update(self.sender_nonce.to_be_bytes());
+        hasher.update(self.receiver_nonce.to_be_bytes());
+        B256::from_slice(&hasher.finalize())
+    }
+}
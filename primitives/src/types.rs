@@ -10,7 +10,7 @@ pub type ChainId = u64;
 pub type StorageKey = B256;
 pub type StorageValue = U256;
 const ADDR_LEN: usize = 20;
-#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Address([u8; ADDR_LEN]);
 
 impl Address {
@@ -0,0 +1,120 @@
+//! Reads of many threads while one writer keeps committing blocks.
+use std::{
+    collections::HashMap,
+    hint::black_box,
+    sync::{
+        Arc, Barrier,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use primitives::{
+    account::Account,
+    block::{body::BlockBody, header::Header},
+    types::{Address, U256},
+};
+use storage::db::{Database, DatabaseMut, ExecutedBlock, InMemoryDB};
+
+const ACCOUNTS: usize = 1_000;
+// accounts touched by every block of the writer
+const DIFF_SIZE: usize = 100;
+
+fn setup() -> (InMemoryDB, Vec<Address>) {
+    let mut db = InMemoryDB::new();
+    let addresses: Vec<_> = (0..ACCOUNTS).map(|_| Address::random()).collect();
+    for address in &addresses {
+        db.set_balance(address.clone(), U256::from(1_000_000))
+            .unwrap();
+    }
+    (db, addresses)
+}
+
+fn commit_next_block(db: &InMemoryDB, addresses: &[Address]) {
+    let number = db.block_number() + 1;
+    let header = Header {
+        previous_hash: db.block_hash(number - 1).unwrap().unwrap_or_default(),
+        number,
+        ..Default::default()
+    };
+    let diff: HashMap<_, _> = addresses
+        .iter()
+        .cycle()
+        .skip(number as usize * DIFF_SIZE % ACCOUNTS)
+        .take(DIFF_SIZE)
+        .map(|address| {
+            let account = Account {
+                nonce: number,
+                balance: U256::from(number),
+            };
+            (address.clone(), account)
+        })
+        .collect();
+    let block = ExecutedBlock::new(header, BlockBody::default(), Vec::new(), diff);
+    db.commit_block(block).unwrap();
+}
+
+// Runs `iters` lookups on every reader thread and returns the time until all of them finished.
+fn run(readers: usize, with_writer: bool, iters: u64) -> Duration {
+    let (db, addresses) = setup();
+    let addresses = Arc::new(addresses);
+    let done = Arc::new(AtomicBool::new(false));
+    let start = Arc::new(Barrier::new(readers + 1));
+
+    let writer = with_writer.then(|| {
+        let (db, addresses, done) = (db.clone(), addresses.clone(), done.clone());
+        thread::spawn(move || {
+            while !done.load(Ordering::Relaxed) {
+                commit_next_block(&db, &addresses);
+            }
+        })
+    });
+
+    let handles: Vec<_> = (0..readers)
+        .map(|reader| {
+            let (db, addresses, start) = (db.clone(), addresses.clone(), start.clone());
+            thread::spawn(move || {
+                start.wait();
+                for i in 0..iters as usize {
+                    let address = &addresses[(i * 7 + reader) % ACCOUNTS];
+                    black_box(db.basic(address).unwrap());
+                }
+            })
+        })
+        .collect();
+
+    start.wait();
+    let started = Instant::now();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    let elapsed = started.elapsed();
+
+    done.store(true, Ordering::Relaxed);
+    if let Some(writer) = writer {
+        writer.join().unwrap();
+    }
+    elapsed
+}
+
+fn contention(c: &mut Criterion) {
+    let mut group = c.benchmark_group("contention");
+    for readers in [1, 2, 4, 8] {
+        group.bench_with_input(
+            BenchmarkId::new("readers", readers),
+            &readers,
+            |b, &readers| b.iter_custom(|iters| run(readers, false, iters)),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("readers_with_writer", readers),
+            &readers,
+            |b, &readers| b.iter_custom(|iters| run(readers, true, iters)),
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, contention);
+criterion_main!(benches);
@@ -0,0 +1,495 @@
+//! Read-through caching in front of a [StateProviderFactory].
+//! Accounts of the latest state and canonical headers are kept in LRU caches that every clone of
+//! the [CachedStateProviderFactory] shares, so the pool validator, the payload builder and RPC
+//! all read through the same caches.
+//!
+//! The caches follow the canonical head. Accounts are dropped whenever the head changes and
+//! headers only when the new head does not extend the old one, because a reorg replaced some of
+//! them.
+use std::{
+    collections::HashMap,
+    num::NonZeroUsize,
+    sync::{
+        Arc, Mutex, MutexGuard,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use lru::LruCache;
+use primitives::{
+    account::Account,
+    block::{
+        body::SealedBlock,
+        header::{Header, SealedHeader},
+        traits::Block,
+    },
+    receipt::Receipt,
+    types::{Address, B256, BlockHash, StorageKey, StorageValue, TxHash},
+};
+
+use crate::{
+    db::{AccountsPage, TransactionMeta},
+    error::{DatabaseError, ProviderError},
+    traits::{
+        AccountReader, BlockReader, HeaderProvider, ProviderResult, ReceiptProvider, StateProvider,
+        StateProviderBox, StateProviderFactory, TransactionsProvider,
+    },
+};
+
+/// Accounts of the latest state that are cached by default
+pub const DEFAULT_ACCOUNT_CACHE_SIZE: usize = 10_000;
+/// Canonical headers that are cached by default
+pub const DEFAULT_HEADER_CACHE_SIZE: usize = 1_024;
+
+/// Hit and miss counters of the caches
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CacheStats {
+    pub account_hits: u64,
+    pub account_misses: u64,
+    pub header_hits: u64,
+    pub header_misses: u64,
+}
+
+#[derive(Default)]
+struct Counters {
+    account_hits: AtomicU64,
+    account_misses: AtomicU64,
+    header_hits: AtomicU64,
+    header_misses: AtomicU64,
+}
+
+struct Caches {
+    // canonical head the account cache belongs to
+    head: Option<BlockHash>,
+    accounts: LruCache<Address, Option<Account>>,
+    headers: LruCache<u64, SealedHeader>,
+}
+
+/// [StateProviderFactory] with an account cache for the latest state and a header cache
+pub struct CachedStateProviderFactory<F> {
+    inner: F,
+    caches: Arc<Mutex<Caches>>,
+    counters: Arc<Counters>,
+}
+
+impl<F: Clone> Clone for CachedStateProviderFactory<F> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            caches: self.caches.clone(),
+            counters: self.counters.clone(),
+        }
+    }
+}
+
+impl<F> CachedStateProviderFactory<F> {
+    pub fn new(inner: F) -> Self {
+        Self::with_capacity(inner, DEFAULT_ACCOUNT_CACHE_SIZE, DEFAULT_HEADER_CACHE_SIZE)
+    }
+
+    /// Caches up to the given number of accounts and headers. Zero is treated as one.
+    pub fn with_capacity(inner: F, accounts: usize, headers: usize) -> Self {
+        let capacity = |size: usize| NonZeroUsize::new(size).unwrap_or(NonZeroUsize::MIN);
+        Self {
+            inner,
+            caches: Arc::new(Mutex::new(Caches {
+                head: None,
+                accounts: LruCache::new(capacity(accounts)),
+                headers: LruCache::new(capacity(headers)),
+            })),
+            counters: Default::default(),
+        }
+    }
+
+    pub fn inner(&self) -> &F {
+        &self.inner
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let counters = &self.counters;
+        CacheStats {
+            account_hits: counters.account_hits.load(Ordering::Relaxed),
+            account_misses: counters.account_misses.load(Ordering::Relaxed),
+            header_hits: counters.header_hits.load(Ordering::Relaxed),
+            header_misses: counters.header_misses.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Drops every cached entry
+    pub fn clear(&self) -> ProviderResult<()> {
+        let mut caches = lock(&self.caches)?;
+        caches.head = None;
+        caches.accounts.clear();
+        caches.headers.clear();
+        Ok(())
+    }
+}
+
+impl<F> CachedStateProviderFactory<F>
+where
+    F: StateProviderFactory + HeaderProvider<Header = Header>,
+{
+    // Moves the caches to the current canonical head and returns it.
+    fn sync_head(&self) -> ProviderResult<Option<SealedHeader>> {
+        let head = self.inner.latest_header()?;
+        let mut caches = lock(&self.caches)?;
+        let hash = head.as_ref().map(SealedHeader::hash);
+        if caches.head != hash {
+            caches.accounts.clear();
+            let extends = head
+                .as_ref()
+                .is_some_and(|head| caches.head == Some(head.header().previous_hash));
+            if !extends {
+                caches.headers.clear();
+            }
+            caches.head = hash;
+        }
+        Ok(head)
+    }
+
+    // State of the head that reads accounts through the cache
+    fn cached_state(&self, head: &SealedHeader) -> ProviderResult<StateProviderBox> {
+        // By hash, so a block committed meanwhile does not end up in the cache of this head.
+        let state = self.inner.state_by_block_hash(head.hash())?;
+        Ok(Box::new(CachedStateProvider {
+            state,
+            head: head.hash(),
+            caches: self.caches.clone(),
+            counters: self.counters.clone(),
+        }))
+    }
+}
+
+impl<F> StateProviderFactory for CachedStateProviderFactory<F>
+where
+    F: StateProviderFactory + HeaderProvider<Header = Header>,
+{
+    fn latest(&self) -> ProviderResult<StateProviderBox> {
+        match self.sync_head()? {
+            Some(head) => self.cached_state(&head),
+            None => self.inner.latest(),
+        }
+    }
+
+    fn state_by_block_number(&self, block: u64) -> ProviderResult<StateProviderBox> {
+        if let Some(head) = self.sync_head()?
+            && head.number() == block
+        {
+            return self.cached_state(&head);
+        }
+        self.inner.state_by_block_number(block)
+    }
+
+    fn state_by_block_hash(&self, hash: BlockHash) -> ProviderResult<StateProviderBox> {
+        if let Some(head) = self.sync_head()?
+            && head.hash() == hash
+        {
+            return self.cached_state(&head);
+        }
+        self.inner.state_by_block_hash(hash)
+    }
+}
+
+impl<F> HeaderProvider for CachedStateProviderFactory<F>
+where
+    F: StateProviderFactory + HeaderProvider<Header = Header>,
+{
+    type Header = Header;
+
+    fn latest_header(&self) -> ProviderResult<Option<SealedHeader>> {
+        self.sync_head()
+    }
+
+    fn sealed_header_by_hash(&self, block_hash: BlockHash) -> ProviderResult<Option<SealedHeader>> {
+        let Some(number) = self.inner.block_number(block_hash)? else {
+            return Ok(None);
+        };
+        Ok(self
+            .sealed_header(number)?
+            .filter(|header| header.hash() == block_hash))
+    }
+
+    fn sealed_header(&self, number: u64) -> ProviderResult<Option<SealedHeader>> {
+        // A reorg must clear the replaced headers before they are served.
+        self.sync_head()?;
+        if let Some(header) = lock(&self.caches)?.headers.get(&number) {
+            self.counters.header_hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(Some(header.clone()));
+        }
+
+        self.counters.header_misses.fetch_add(1, Ordering::Relaxed);
+        let header = self.inner.sealed_header(number)?;
+        if let Some(header) = &header {
+            lock(&self.caches)?.headers.put(number, header.clone());
+        }
+        Ok(header)
+    }
+
+    fn block_number(&self, hash: BlockHash) -> ProviderResult<Option<u64>> {
+        self.inner.block_number(hash)
+    }
+}
+
+impl<F> BlockReader for CachedStateProviderFactory<F>
+where
+    F: StateProviderFactory + BlockReader<Header = Header>,
+{
+    type Block = F::Block;
+
+    fn body_by_number(&self, number: u64) -> ProviderResult<Option<<F::Block as Block>::Body>> {
+        self.inner.body_by_number(number)
+    }
+
+    fn block_by_number(&self, number: u64) -> ProviderResult<Option<SealedBlock<F::Block>>> {
+        self.inner.block_by_number(number)
+    }
+
+    fn block_by_hash(&self, hash: BlockHash) -> ProviderResult<Option<SealedBlock<F::Block>>> {
+        self.inner.block_by_hash(hash)
+    }
+}
+
+impl<F: TransactionsProvider> TransactionsProvider for CachedStateProviderFactory<F> {
+    type Transaction = F::Transaction;
+
+    fn transaction_by_hash(&self, hash: TxHash) -> ProviderResult<Option<F::Transaction>> {
+        self.inner.transaction_by_hash(hash)
+    }
+
+    fn transaction_block(&self, hash: TxHash) -> ProviderResult<Option<TransactionMeta>> {
+        self.inner.transaction_block(hash)
+    }
+
+    fn receipt_by_hash(&self, hash: TxHash) -> ProviderResult<Option<Receipt>> {
+        self.inner.receipt_by_hash(hash)
+    }
+}
+
+impl<F: ReceiptProvider> ReceiptProvider for CachedStateProviderFactory<F> {
+    fn receipts_by_block(&self, number: u64) -> ProviderResult<Option<Vec<Receipt>>> {
+        self.inner.receipts_by_block(number)
+    }
+
+    fn receipt(&self, tx_hash: TxHash) -> ProviderResult<Option<Receipt>> {
+        self.inner.receipt(tx_hash)
+    }
+}
+
+/// State of the canonical head that reads accounts through the shared cache
+struct CachedStateProvider {
+    state: StateProviderBox,
+    head: BlockHash,
+    caches: Arc<Mutex<Caches>>,
+    counters: Arc<Counters>,
+}
+
+impl StateProvider for CachedStateProvider {
+    fn account_storage(
+        &self,
+        addr: &Address,
+        key: &StorageKey,
+    ) -> ProviderResult<Option<StorageValue>> {
+        self.state.account_storage(addr, key)
+    }
+
+    fn prepare_execute(&mut self) -> ProviderResult<HashMap<Address, Account>> {
+        self.state.prepare_execute()
+    }
+
+    fn state_root(&self, diff: &HashMap<Address, Account>) -> ProviderResult<B256> {
+        self.state.state_root(diff)
+    }
+}
+
+impl AccountReader for CachedStateProvider {
+    fn basic_account(&self, address: &Address) -> ProviderResult<Option<Account>> {
+        {
+            let mut caches = lock(&self.caches)?;
+            // The cache moved on if the head changed after this state was created.
+            if caches.head == Some(self.head)
+                && let Some(account) = caches.accounts.get(address)
+            {
+                self.counters.account_hits.fetch_add(1, Ordering::Relaxed);
+                return Ok(*account);
+            }
+        }
+
+        self.counters.account_misses.fetch_add(1, Ordering::Relaxed);
+        let account = self.state.basic_account(address)?;
+        let mut caches = lock(&self.caches)?;
+        if caches.head == Some(self.head) {
+            caches.accounts.put(address.clone(), account);
+        }
+        Ok(account)
+    }
+
+    fn accounts_page(&self, start: &Address, limit: usize) -> ProviderResult<AccountsPage> {
+        self.state.accounts_page(start, limit)
+    }
+
+    fn total_accounts(&self) -> ProviderResult<u64> {
+        self.state.total_accounts()
+    }
+}
+
+fn lock(caches: &Mutex<Caches>) -> ProviderResult<MutexGuard<'_, Caches>> {
+    caches
+        .lock()
+        .map_err(|_| ProviderError::DatabaseError(DatabaseError::LockError))
+}
+
+#[cfg(test)]
+mod tests {
+    use primitives::{block::body::BlockBody, types::U256};
+
+    use super::*;
+    use crate::{
+        PintStateProviderFactory,
+        db::{Database, DatabaseMut, ExecutedBlock, InMemoryDB},
+    };
+
+    fn commit_block(db: &InMemoryDB, timestamp: u64, diff: HashMap<Address, Account>) {
+        let number = db.block_number() + 1;
+        let header = Header {
+            previous_hash: db.block_hash(number - 1).unwrap().unwrap_or_default(),
+            number,
+            timestamp,
+            ..Default::default()
+        };
+        db.commit_block(ExecutedBlock::new(
+            header,
+            BlockBody::default(),
+            Vec::new(),
+            diff,
+        ))
+        .unwrap();
+    }
+
+    fn account(balance: u64) -> Account {
+        Account {
+            nonce: 0,
+            balance: U256::from(balance),
+        }
+    }
+
+    fn setup() -> (
+        InMemoryDB,
+        CachedStateProviderFactory<PintStateProviderFactory<InMemoryDB>>,
+        Address,
+    ) {
+        let db = InMemoryDB::new();
+        let address = Address::random();
+        commit_block(&db, 1, HashMap::from([(address.clone(), account(10))]));
+        let factory = CachedStateProviderFactory::new(PintStateProviderFactory::new(db.clone()));
+        (db, factory, address)
+    }
+
+    #[test]
+    fn test_account_cache_is_shared_by_clones() {
+        let (_db, factory, address) = setup();
+        let shared = factory.clone();
+
+        assert_eq!(
+            factory.latest().unwrap().basic_account(&address).unwrap(),
+            Some(account(10))
+        );
+        assert_eq!(
+            shared.latest().unwrap().account_balance(&address).unwrap(),
+            Some(U256::from(10))
+        );
+        // missing accounts are cached too
+        let missing = Address::random();
+        let state = shared.latest().unwrap();
+        assert_eq!(state.basic_account(&missing).unwrap(), None);
+        assert_eq!(state.basic_account(&missing).unwrap(), None);
+
+        let stats = factory.stats();
+        assert_eq!(stats.account_hits, 2);
+        assert_eq!(stats.account_misses, 2);
+    }
+
+    #[test]
+    fn test_head_change_invalidates_accounts() {
+        let (db, factory, address) = setup();
+        let old_state = factory.latest().unwrap();
+        assert_eq!(
+            old_state.basic_account(&address).unwrap(),
+            Some(account(10))
+        );
+
+        commit_block(&db, 2, HashMap::from([(address.clone(), account(20))]));
+        let state = factory.latest().unwrap();
+        assert_eq!(state.basic_account(&address).unwrap(), Some(account(20)));
+        assert_eq!(state.basic_account(&address).unwrap(), Some(account(20)));
+        // the state of the old head still reads its own block and leaves the cache alone
+        assert_eq!(
+            old_state.basic_account(&address).unwrap(),
+            Some(account(10))
+        );
+        assert_eq!(
+            factory.stats(),
+            CacheStats {
+                account_hits: 1,
+                account_misses: 3,
+                ..Default::default()
+            }
+        );
+
+        // the payload builder asks for the head by hash and shares the cache
+        let head = db.block_hash(2).unwrap().unwrap();
+        let state = factory.state_by_block_hash(head).unwrap();
+        assert_eq!(state.basic_account(&address).unwrap(), Some(account(20)));
+        assert_eq!(factory.stats().account_hits, 2);
+
+        // historical states bypass the cache
+        let state = factory.state_by_block_number(1).unwrap();
+        assert_eq!(state.basic_account(&address).unwrap(), Some(account(10)));
+        assert_eq!(factory.stats().account_misses, 3);
+    }
+
+    #[test]
+    fn test_header_cache_follows_reorgs() {
+        let (db, factory, _) = setup();
+        commit_block(&db, 2, HashMap::new());
+
+        let header = factory.sealed_header(2).unwrap().unwrap();
+        assert_eq!(
+            factory.sealed_header(2).unwrap().unwrap().hash(),
+            header.hash()
+        );
+        assert_eq!(
+            factory
+                .sealed_header_by_hash(header.hash())
+                .unwrap()
+                .unwrap()
+                .number(),
+            2
+        );
+        assert!(factory.sealed_header(3).unwrap().is_none());
+
+        // extending the chain keeps the cached headers
+        commit_block(&db, 3, HashMap::new());
+        factory.sealed_header(2).unwrap();
+        assert_eq!(
+            factory.stats(),
+            CacheStats {
+                header_hits: 3,
+                header_misses: 2,
+                ..Default::default()
+            }
+        );
+
+        // a reorg replaces block 2
+        db.unwind_to(1).unwrap();
+        commit_block(&db, 22, HashMap::new());
+        let reorged = factory.sealed_header(2).unwrap().unwrap();
+        assert_eq!(reorged.header().timestamp, 22);
+        assert!(
+            factory
+                .sealed_header_by_hash(header.hash())
+                .unwrap()
+                .is_none()
+        );
+        assert_eq!(factory.stats().header_misses, 3);
+    }
+}
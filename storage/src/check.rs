@@ -0,0 +1,661 @@
+//! Integrity check of the stored chain.
+//! [check_database] walks every canonical block and collects [Finding]s instead of stopping at
+//! the first problem. The block hash and transaction indices are derived from the blocks, so
+//! findings in them can be fixed with [repair]. Everything else needs a resync.
+use std::{collections::BTreeSet, fmt};
+
+use primitives::{
+    account::Account,
+    proofs::{calculate_receipts_root, calculate_state_root, calculate_transaction_root},
+    types::{Address, B256, BlockHash, TxHash},
+};
+
+use crate::{
+    db::{AccountChangeset, Database, DatabaseMut, TransactionMeta},
+    error::DatabaseError,
+};
+
+/// Problem found in the stored chain
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Finding {
+    // canonical block number without a header or block hash
+    MissingHeader(u64),
+    // stored block hash is not the hash of the header
+    BlockHashMismatch {
+        number: u64,
+        stored: BlockHash,
+        computed: BlockHash,
+    },
+    // header does not link to the previous canonical block
+    ParentHashMismatch {
+        number: u64,
+        expected: BlockHash,
+        found: BlockHash,
+    },
+    MissingBody(u64),
+    TransactionRootMismatch {
+        number: u64,
+        expected: B256,
+        computed: B256,
+    },
+    // number of receipts differs from the number of transactions
+    ReceiptsLengthMismatch {
+        number: u64,
+        transactions: usize,
+        receipts: usize,
+    },
+    ReceiptsRootMismatch {
+        number: u64,
+        expected: B256,
+        computed: B256,
+    },
+    // state of the block cannot be rebuilt from the changesets
+    StateUnavailable(u64),
+    StateRootMismatch {
+        number: u64,
+        expected: B256,
+        computed: B256,
+    },
+    // lookup through the history index disagrees with the reverted changesets
+    HistoryMismatch {
+        number: u64,
+        address: Address,
+        expected: Option<Account>,
+        found: Option<Account>,
+    },
+    // history index points to a changeset that does not hold the account
+    HistoryUnavailable {
+        number: u64,
+        address: Address,
+    },
+    // block hash index does not point to the canonical block
+    HeaderIndexMismatch {
+        number: u64,
+        hash: BlockHash,
+        found: Option<u64>,
+    },
+    // transaction index does not point to the canonical position of the transaction
+    TxIndexMismatch {
+        hash: TxHash,
+        expected: TransactionMeta,
+        found: Option<TransactionMeta>,
+    },
+}
+
+impl Finding {
+    /// Whether [repair] can fix it
+    pub fn is_repairable(&self) -> bool {
+        matches!(
+            self,
+            Finding::HeaderIndexMismatch { .. } | Finding::TxIndexMismatch { .. }
+        )
+    }
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Finding::MissingHeader(number) => write!(f, "block {}: header is missing", number),
+            Finding::BlockHashMismatch {
+                number,
+                stored,
+                computed,
+            } => write!(
+                f,
+                "block {}: stored hash {} differs from the header hash {}",
+                number, stored, computed
+            ),
+            Finding::ParentHashMismatch {
+                number,
+                expected,
+                found,
+            } => write!(
+                f,
+                "block {}: parent hash {} does not link to block {}",
+                number, found, expected
+            ),
+            Finding::MissingBody(number) => write!(f, "block {}: body is missing", number),
+            Finding::TransactionRootMismatch {
+                number,
+                expected,
+                computed,
+            } => write!(
+                f,
+                "block {}: transaction root {} differs from the header {}",
+                number, computed, expected
+            ),
+            Finding::ReceiptsLengthMismatch {
+                number,
+                transactions,
+                receipts,
+            } => write!(
+                f,
+                "block {}: {} receipts for {} transactions",
+                number, receipts, transactions
+            ),
+            Finding::ReceiptsRootMismatch {
+                number,
+                expected,
+                computed,
+            } => write!(
+                f,
+                "block {}: receipts root {} differs from the header {}",
+                number, computed, expected
+            ),
+            Finding::StateUnavailable(number) => {
+                write!(
+                    f,
+                    "block {}: state cannot be rebuilt from the changesets",
+                    number
+                )
+            }
+            Finding::StateRootMismatch {
+                number,
+                expected,
+                computed,
+            } => write!(
+                f,
+                "block {}: state root {} differs from the header {}",
+                number, computed, expected
+            ),
+            Finding::HistoryMismatch {
+                number,
+                address,
+                expected,
+                found,
+            } => write!(
+                f,
+                "block {}: history of {:?} returns {:?} instead of {:?}",
+                number, address, found, expected
+            ),
+            Finding::HistoryUnavailable { number, address } => write!(
+                f,
+                "block {}: history of {:?} cannot be read",
+                number, address
+            ),
+            Finding::HeaderIndexMismatch {
+                number,
+                hash,
+                found,
+            } => write!(
+                f,
+                "block {}: hash index of {} points to {:?}",
+                number, hash, found
+            ),
+            Finding::TxIndexMismatch {
+                hash,
+                expected,
+                found,
+            } => write!(
+                f,
+                "block {}: index of transaction {} points to {:?} instead of position {}",
+                expected.block_number, hash, found, expected.index
+            ),
+        }
+    }
+}
+
+/// Result of [check_database]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CheckReport {
+    // range of the checked blocks
+    pub first_block: u64,
+    pub last_block: u64,
+    pub findings: Vec<Finding>,
+}
+
+impl CheckReport {
+    pub fn is_ok(&self) -> bool {
+        self.findings.is_empty()
+    }
+
+    /// Whether [repair] can fix every finding
+    pub fn is_repairable(&self) -> bool {
+        self.findings.iter().all(Finding::is_repairable)
+    }
+}
+
+/// Walks the canonical chain from its first stored block to the head.
+/// Errors are only returned if the database cannot be read at all.
+pub fn check_database<DB: Database>(db: &DB) -> Result<CheckReport, DatabaseError> {
+    let latest = db.block_number();
+    // Databases seeded from a snapshot start above genesis.
+    let mut first = 0;
+    while first < latest && db.block_hash(first)?.is_none() {
+        first += 1;
+    }
+
+    let mut report = CheckReport {
+        first_block: first,
+        last_block: latest,
+        findings: Vec::new(),
+    };
+    let mut parent = None;
+    for number in first..=latest {
+        parent = check_block(db, number, first, parent, &mut report.findings)?;
+    }
+
+    let earliest_state = db.earliest_state_block()?.max(first);
+    check_state(db, earliest_state, latest, &mut report.findings)?;
+    Ok(report)
+}
+
+/// Fixes the repairable findings of the report by rebuilding the indices.
+/// Returns the number of index entries that changed.
+pub fn repair<DB: DatabaseMut>(db: &DB, report: &CheckReport) -> Result<u64, DatabaseError> {
+    if !report.findings.iter().any(Finding::is_repairable) {
+        return Ok(0);
+    }
+    db.rebuild_indices()
+}
+
+// Checks the header, body, receipts and indices of a block and returns its hash.
+fn check_block<DB: Database>(
+    db: &DB,
+    number: u64,
+    first: u64,
+    parent: Option<BlockHash>,
+    findings: &mut Vec<Finding>,
+) -> Result<Option<BlockHash>, DatabaseError> {
+    let (Some(header), Some(hash)) = (db.header(number)?, db.block_hash(number)?) else {
+        findings.push(Finding::MissingHeader(number));
+        return Ok(None);
+    };
+
+    let computed = header.hash_slow();
+    if computed != hash {
+        findings.push(Finding::BlockHashMismatch {
+            number,
+            stored: hash,
+            computed,
+        });
+    }
+    if let Some(parent) = parent
+        && header.previous_hash != parent
+    {
+        findings.push(Finding::ParentHashMismatch {
+            number,
+            expected: parent,
+            found: header.previous_hash,
+        });
+    }
+    let found = db.block_number_by_hash(&hash)?;
+    if found != Some(number) {
+        findings.push(Finding::HeaderIndexMismatch {
+            number,
+            hash,
+            found,
+        });
+    }
+
+    let Some(body) = db.body(number)? else {
+        // the block a snapshot seeded the database with has no body
+        if number != first {
+            findings.push(Finding::MissingBody(number));
+        }
+        return Ok(Some(hash));
+    };
+    let computed = calculate_transaction_root(body.transaction.iter().map(|tx| tx.hash()));
+    if computed != header.transaction_root {
+        findings.push(Finding::TransactionRootMismatch {
+            number,
+            expected: header.transaction_root,
+            computed,
+        });
+    }
+
+    // Pruned receipts took their transaction index entries with them.
+    let Some(receipts) = db.receipts(number)? else {
+        return Ok(Some(hash));
+    };
+    if receipts.len() != body.transaction.len() {
+        findings.push(Finding::ReceiptsLengthMismatch {
+            number,
+            transactions: body.transaction.len(),
+            receipts: receipts.len(),
+        });
+    }
+    let computed = calculate_receipts_root(&receipts);
+    if computed != header.receipts_root {
+        findings.push(Finding::ReceiptsRootMismatch {
+            number,
+            expected: header.receipts_root,
+            computed,
+        });
+    }
+    for (index, tx) in body.transaction.iter().enumerate() {
+        let expected = TransactionMeta {
+            block_hash: hash,
+            block_number: number,
+            index: index as u64,
+        };
+        let found = db.transaction_meta(&tx.hash())?;
+        if found != Some(expected) {
+            findings.push(Finding::TxIndexMismatch {
+                hash: tx.hash(),
+                expected,
+                found,
+            });
+        }
+    }
+    Ok(Some(hash))
+}
+
+// Walks the state from the head down to the earliest stored state, reverting one changeset per
+// block, and matches the state of every block against its header and the history index.
+fn check_state<DB: Database>(
+    db: &DB,
+    earliest: u64,
+    latest: u64,
+    findings: &mut Vec<Finding>,
+) -> Result<(), DatabaseError> {
+    let (Ok(mut state), Ok(mut storage)) = (
+        db.copy_state_from_block_no(latest),
+        db.copy_storage_from_block_no(latest),
+    ) else {
+        findings.extend((earliest..=latest).map(Finding::StateUnavailable));
+        return Ok(());
+    };
+
+    // findings of every block, from the head down
+    let mut blocks = Vec::new();
+    // changeset of the block above, whose accounts may have been removed from the state
+    let mut above = AccountChangeset::new();
+    let mut number = latest;
+    loop {
+        let mut block = Vec::new();
+        if let Some(header) = db.header(number)? {
+            let computed = calculate_state_root(&state, &storage);
+            if computed != header.state_root {
+                block.push(Finding::StateRootMismatch {
+                    number,
+                    expected: header.state_root,
+                    computed,
+                });
+            }
+        }
+        let addresses: BTreeSet<_> = state.keys().chain(above.keys()).collect();
+        for address in addresses {
+            let expected = state.get(address).copied();
+            match db.basic_at_block(address, number) {
+                Ok(found) if found == expected => {}
+                Ok(found) => block.push(Finding::HistoryMismatch {
+                    number,
+                    address: address.clone(),
+                    expected,
+                    found,
+                }),
+                Err(_) => block.push(Finding::HistoryUnavailable {
+                    number,
+                    address: address.clone(),
+                }),
+            }
+        }
+        blocks.push(block);
+        if number == earliest {
+            break;
+        }
+
+        let (Some(accounts), Some(slots)) =
+            (db.account_changeset(number)?, db.storage_changeset(number)?)
+        else {
+            blocks.extend(
+                (earliest..number)
+                    .rev()
+                    .map(|n| vec![Finding::StateUnavailable(n)]),
+            );
+            break;
+        };
+        for (address, before) in &accounts {
+            match before {
+                Some(account) => state.insert(address.clone(), *account),
+                None => state.remove(address),
+            };
+        }
+        for ((address, key), before) in slots {
+            match before.filter(|value| !value.is_zero()) {
+                Some(value) => {
+                    storage.entry(address).or_default().insert(key, value);
+                }
+                None => {
+                    if let Some(account) = storage.get_mut(&address) {
+                        account.remove(&key);
+                        if account.is_empty() {
+                            storage.remove(&address);
+                        }
+                    }
+                }
+            }
+        }
+        above = accounts;
+        number -= 1;
+    }
+    findings.extend(blocks.into_iter().rev().flatten());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use primitives::{
+        account::AccountStorage,
+        block::{body::BlockBody, header::Header},
+        receipt::Receipt,
+        types::{StorageKey, U256},
+    };
+
+    use super::*;
+    use crate::{
+        db::{ExecutedBlock, InMemoryDB},
+        genesis::{Genesis, GenesisAccount, init_genesis},
+        tests::make_transactions,
+    };
+
+    // Commits a block whose header commits to its transactions, receipts and post-state.
+    fn commit_block(db: &InMemoryDB, with_txs: bool) {
+        let number = db.block_number() + 1;
+        let address = Address::from_byte([number as u8; 20]);
+        let diff = HashMap::from([(
+            address.clone(),
+            Account {
+                nonce: number,
+                balance: U256::from(number),
+            },
+        )]);
+        let storage_diff = HashMap::from([(
+            address,
+            AccountStorage::from([(StorageKey::with_last_byte(1), U256::from(number))]),
+        )]);
+        let transaction = if with_txs {
+            make_transactions()
+        } else {
+            Vec::new()
+        };
+        let receipts: Vec<_> = transaction
+            .iter()
+            .map(|tx| Receipt {
+                tx_hash: tx.hash(),
+                success: true,
+                ..Default::default()
+            })
+            .collect();
+
+        let mut state = db.copy_state_from_block_no(number - 1).unwrap();
+        state.extend(diff.clone());
+        let mut storage = db.copy_storage_from_block_no(number - 1).unwrap();
+        for (address, slots) in &storage_diff {
+            storage
+                .entry(address.clone())
+                .or_default()
+                .extend(slots.clone());
+        }
+        let header = Header {
+            previous_hash: db.block_hash(number - 1).unwrap().unwrap(),
+            number,
+            transaction_root: calculate_transaction_root(transaction.iter().map(|tx| tx.hash())),
+            state_root: calculate_state_root(&state, &storage),
+            receipts_root: calculate_receipts_root(&receipts),
+            timestamp: number,
+            ..Default::default()
+        };
+        let block = ExecutedBlock::new(header, BlockBody { transaction }, receipts, diff)
+            .with_storage_diff(storage_diff);
+        db.commit_block(block).unwrap();
+    }
+
+    fn valid_chain() -> InMemoryDB {
+        let db = InMemoryDB::new();
+        let genesis = Genesis::default().with_account(
+            Address::from_byte([0xaa; 20]),
+            GenesisAccount {
+                balance: U256::from(100),
+                ..Default::default()
+            },
+        );
+        init_genesis(&db, &genesis).unwrap();
+        commit_block(&db, false);
+        commit_block(&db, true);
+        commit_block(&db, false);
+        db
+    }
+
+    #[test]
+    fn test_valid_chain_has_no_findings() {
+        let report = check_database(&valid_chain()).unwrap();
+        assert!(report.is_ok(), "{:?}", report.findings);
+        assert_eq!((report.first_block, report.last_block), (0, 3));
+    }
+
+    #[test]
+    fn test_repair_index_corruption() {
+        let db = valid_chain();
+        let block = db.block_hash(2).unwrap().unwrap();
+        let tx = make_transactions()[1].hash();
+        db.remove_index_entries(&block, &tx);
+
+        let report = check_database(&db).unwrap();
+        assert_eq!(
+            report.findings,
+            vec![
+                Finding::HeaderIndexMismatch {
+                    number: 2,
+                    hash: block,
+                    found: None
+                },
+                Finding::TxIndexMismatch {
+                    hash: tx,
+                    expected: TransactionMeta {
+                        block_hash: block,
+                        block_number: 2,
+                        index: 1
+                    },
+                    found: None
+                },
+            ]
+        );
+        assert!(report.is_repairable());
+
+        assert_eq!(repair(&db, &report).unwrap(), 2);
+        assert!(check_database(&db).unwrap().is_ok());
+    }
+
+    #[test]
+    fn test_corrupted_state_is_reported() {
+        let mut db = valid_chain();
+        let address = Address::from_byte([0xaa; 20]);
+        db.set_balance(address, U256::from(1)).unwrap();
+
+        let report = check_database(&db).unwrap();
+        // the account never changed, so every block reverts to the corrupted balance
+        let numbers: Vec<_> = report
+            .findings
+            .iter()
+            .map(|finding| match finding {
+                Finding::StateRootMismatch { number, .. } => *number,
+                other => panic!("unexpected finding {}", other),
+            })
+            .collect();
+        assert_eq!(numbers, vec![0, 1, 2, 3]);
+        assert!(!report.is_repairable());
+        assert_eq!(repair(&db, &report).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_unreadable_history_is_reported() {
+        let db = valid_chain();
+        let address = Address::from_byte([0xaa; 20]);
+        db.add_history_entry(&address, 3);
+
+        let report = check_database(&db).unwrap();
+        assert_eq!(
+            report.findings,
+            (0..3)
+                .map(|number| Finding::HistoryUnavailable {
+                    number,
+                    address: address.clone()
+                })
+                .collect::<Vec<_>>()
+        );
+        assert!(!report.is_repairable());
+    }
+
+    #[test]
+    fn test_missing_roots_are_reported() {
+        let db = InMemoryDB::new();
+        init_genesis(
+            &db,
+            &Genesis::default()
+                .with_account(Address::from_byte([0xaa; 20]), GenesisAccount::default()),
+        )
+        .unwrap();
+        // a block whose header does not commit to anything
+        let header = Header {
+            previous_hash: db.block_hash(0).unwrap().unwrap(),
+            number: 1,
+            ..Default::default()
+        };
+        let transaction = make_transactions();
+        let receipts = vec![Receipt::default(); transaction.len()];
+        db.commit_block(ExecutedBlock::new(
+            header,
+            BlockBody { transaction },
+            receipts,
+            HashMap::new(),
+        ))
+        .unwrap();
+
+        let report = check_database(&db).unwrap();
+        let kinds: Vec<_> = report.findings.iter().map(std::mem::discriminant).collect();
+        let expected = [
+            Finding::TransactionRootMismatch {
+                number: 1,
+                expected: B256::ZERO,
+                computed: B256::ZERO,
+            },
+            Finding::ReceiptsRootMismatch {
+                number: 1,
+                expected: B256::ZERO,
+                computed: B256::ZERO,
+            },
+            Finding::StateRootMismatch {
+                number: 1,
+                expected: B256::ZERO,
+                computed: B256::ZERO,
+            },
+        ];
+        assert_eq!(
+            kinds,
+            expected
+                .iter()
+                .map(std::mem::discriminant)
+                .collect::<Vec<_>>()
+        );
+        assert!(
+            report
+                .findings
+                .iter()
+                .all(|finding| finding.to_string().starts_with("block 1"))
+        );
+    }
+}
@@ -1,15 +1,18 @@
-use core::num;
 use primitives::{
-    account::Account,
-    block::{self, traits::Block},
-    types::{Address, BlockHash, U256},
+    account::{Account, AccountStorage},
+    block::{
+        body::BlockBody,
+        header::{Header, SealedHeader},
+    },
+    receipt::Receipt,
+    types::{Address, BlockHash, StorageKey, StorageValue, TxHash, U256},
 };
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, BTreeSet, HashMap},
     hash::Hash,
-    ops::Add,
-    sync::{Arc, Mutex},
+    sync::{Arc, Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard},
 };
+use transaction::TransactionSigned;
 
 use crate::error::DatabaseError;
 
@@ -17,6 +20,12 @@ use crate::error::DatabaseError;
 pub trait Database {
     // latest account info
     fn basic(&self, address: &Address) -> Result<Option<Account>, DatabaseError>;
+    // account info after the block at given block_number was applied
+    fn basic_at_block(
+        &self,
+        address: &Address,
+        number: u64,
+    ) -> Result<Option<Account>, DatabaseError>;
     // block hash at given block_number
     fn block_hash(&self, number: u64) -> Result<Option<BlockHash>, DatabaseError>;
     fn block_number(&self) -> u64;
@@ -24,100 +33,825 @@ pub trait Database {
         &self,
         number: u64,
     ) -> Result<HashMap<Address, Account>, DatabaseError>;
+    // canonical header at given block_number
+    fn header(&self, number: u64) -> Result<Option<Header>, DatabaseError>;
+    // canonical body at given block_number
+    fn body(&self, number: u64) -> Result<Option<BlockBody<TransactionSigned>>, DatabaseError>;
+    // block number of the given canonical block hash
+    fn block_number_by_hash(&self, hash: &BlockHash) -> Result<Option<u64>, DatabaseError>;
+    // location of a canonical transaction
+    fn transaction_meta(&self, hash: &TxHash) -> Result<Option<TransactionMeta>, DatabaseError>;
+    // receipts of the canonical block at given block_number
+    fn receipts(&self, number: u64) -> Result<Option<Vec<Receipt>>, DatabaseError>;
+    // lowest block_number whose state has not been pruned
+    fn earliest_state_block(&self) -> Result<u64, DatabaseError>;
+    // latest accounts ordered by address, starting at `start`
+    fn accounts_range(&self, start: &Address, limit: usize) -> Result<AccountsPage, DatabaseError>;
+    // number of accounts in the latest state
+    fn account_count(&self) -> Result<u64, DatabaseError>;
+    // accounts ordered by address after the block at given block_number was applied
+    fn accounts_range_at_block(
+        &self,
+        number: u64,
+        start: &Address,
+        limit: usize,
+    ) -> Result<AccountsPage, DatabaseError>;
+    // number of accounts after the block at given block_number was applied
+    fn account_count_at_block(&self, number: u64) -> Result<u64, DatabaseError>;
+    // latest value of a storage slot. None if the slot is empty.
+    fn storage(
+        &self,
+        address: &Address,
+        key: &StorageKey,
+    ) -> Result<Option<StorageValue>, DatabaseError>;
+    // value of a storage slot after the block at given block_number was applied
+    fn storage_at_block(
+        &self,
+        address: &Address,
+        key: &StorageKey,
+        number: u64,
+    ) -> Result<Option<StorageValue>, DatabaseError>;
+    // storage of every account after the block at given block_number was applied
+    fn copy_storage_from_block_no(
+        &self,
+        number: u64,
+    ) -> Result<HashMap<Address, AccountStorage>, DatabaseError>;
+    // accounts changed by the block at given block_number with their info before it.
+    // None if the block has no stored changeset.
+    fn account_changeset(&self, number: u64) -> Result<Option<AccountChangeset>, DatabaseError>;
+    // storage slots changed by the block at given block_number with their value before it
+    fn storage_changeset(&self, number: u64) -> Result<Option<StorageChangeset>, DatabaseError>;
+}
+
+/// Account info before a block was applied. None if the account did not exist.
+pub type AccountChangeset = HashMap<Address, Option<Account>>;
+
+/// Slot value before a block was applied. None if the slot was empty.
+pub type StorageChangeset = HashMap<(Address, StorageKey), Option<StorageValue>>;
+
+/// Write side of the [Database]
+pub trait DatabaseMut: Database {
+    /// Commits a whole executed block on top of the canonical head.
+    /// Either every table is updated or none of them is.
+    fn commit_block(&self, block: ExecutedBlock) -> Result<(), DatabaseError>;
+    /// Removes every canonical block above `number` and reverts the state to it.
+    /// Returns the transactions of the removed blocks, oldest first.
+    fn unwind_to(&self, number: u64) -> Result<Vec<TransactionSigned>, DatabaseError>;
+    /// Drops the state history below `number`, so the state of older blocks cannot be read anymore.
+    /// The head state is never pruned. Returns the number of pruned blocks.
+    fn prune_state_history(&self, number: u64) -> Result<u64, DatabaseError>;
+    /// Drops receipts and transaction index entries of the blocks below `number`.
+    fn prune_receipts(&self, number: u64) -> Result<u64, DatabaseError>;
+    /// Seeds an empty database with the state and storage at the given header.
+    /// There is no history below the header afterwards.
+    /// Block 0 is the genesis block, which also gets its empty body and receipts.
+    fn init_from_state(
+        &self,
+        header: SealedHeader,
+        state: HashMap<Address, Account>,
+        storage: HashMap<Address, AccountStorage>,
+    ) -> Result<(), DatabaseError>;
+    /// Rebuilds the block hash and transaction indices from the canonical blocks.
+    /// Returns the number of index entries that were added, changed or removed.
+    fn rebuild_indices(&self) -> Result<u64, DatabaseError>;
+}
+
+/// Where a transaction was included in the canonical chain
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransactionMeta {
+    pub block_hash: BlockHash,
+    pub block_number: u64,
+    // position of the transaction in the block body
+    pub index: u64,
+}
+
+/// Page of accounts ordered by address
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AccountsPage {
+    pub accounts: Vec<(Address, Account)>,
+    // first address of the next page, None if this is the last page
+    pub next: Option<Address>,
+}
+
+impl AccountsPage {
+    /// Builds a page from up to `limit + 1` sorted accounts.
+    /// The extra account only marks where the next page starts.
+    pub fn from_sorted(mut accounts: Vec<(Address, Account)>, limit: usize) -> Self {
+        let next = (accounts.len() > limit).then(|| accounts.swap_remove(limit).0);
+        accounts.truncate(limit);
+        Self { accounts, next }
+    }
+}
+
+/// Executed block with the post-state of every account it touched
+#[derive(Debug, Clone)]
+pub struct ExecutedBlock {
+    pub hash: BlockHash,
+    pub header: Header,
+    pub body: BlockBody<TransactionSigned>,
+    // one receipt for each transaction of the body
+    pub receipts: Vec<Receipt>,
+    pub state_diff: HashMap<Address, Account>,
+    // new values of every storage slot the block wrote. A zero value clears the slot.
+    pub storage_diff: HashMap<Address, AccountStorage>,
+}
+
+impl ExecutedBlock {
+    pub fn new(
+        header: Header,
+        body: BlockBody<TransactionSigned>,
+        receipts: Vec<Receipt>,
+        state_diff: HashMap<Address, Account>,
+    ) -> Self {
+        Self {
+            hash: header.hash_slow(),
+            header,
+            body,
+            receipts,
+            state_diff,
+            storage_diff: HashMap::new(),
+        }
+    }
+
+    pub fn with_storage_diff(mut self, storage_diff: HashMap<Address, AccountStorage>) -> Self {
+        self.storage_diff = storage_diff;
+        self
+    }
+
+    pub fn number(&self) -> u64 {
+        self.header.number
+    }
+}
+
+/// Tables of the [InMemoryDB]. They are always locked together.
+#[derive(Default)]
+struct Tables {
+    // state after the latest block, ordered for range queries
+    plain_state: BTreeMap<Address, Account>,
+    // account info before the block was applied. None if the account did not exist.
+    account_changesets: HashMap<u64, HashMap<Address, Option<Account>>>,
+    // account -> block numbers that changed it, i.e. the keys of its changesets
+    account_history: HashMap<Address, BTreeSet<u64>>,
+    // non-empty storage slots after the latest block
+    plain_storage: HashMap<Address, AccountStorage>,
+    // slot value before the block was applied. None if the slot was empty.
+    storage_changesets: HashMap<u64, HashMap<(Address, StorageKey), Option<StorageValue>>>,
+    // slot -> block numbers that changed it
+    storage_history: HashMap<(Address, StorageKey), BTreeSet<u64>>,
+    headers: HashMap<u64, Header>,
+    bodies: HashMap<u64, BlockBody<TransactionSigned>>,
+    receipts: HashMap<u64, Vec<Receipt>>,
+    // transaction hash -> location in the canonical chain
+    tx_index: HashMap<TxHash, TransactionMeta>,
+    // canonical block number -> block hash
+    block_hash: HashMap<u64, BlockHash>,
+    // block hash -> block number
+    header_numbers: HashMap<BlockHash, u64>,
+    latest: u64,
+    // every block below these was pruned
+    state_pruned_below: u64,
+    receipts_pruned_below: u64,
 }
 
 /// In Memory Database for small project.
+/// Clones share the same tables, so every clone sees the new head after a commit.
+///
+/// Readers share a read lock and never wait for each other.
+/// Writers are serialized by a separate lock and prepare their changes under the read lock,
+/// so the exclusive lock is only held while the prepared changes are applied.
 #[derive(Clone, Default)]
 pub struct InMemoryDB {
-    states_by_block: Arc<Mutex<HashMap<u64, HashMap<Address, Account>>>>,
-    block_hash: Arc<Mutex<HashMap<u64, BlockHash>>>,
-    latest: u64,
+    tables: Arc<RwLock<Tables>>,
+    writer: Arc<Mutex<()>>,
 }
 
 impl InMemoryDB {
     pub fn new() -> Self {
-        let states_by_block: Arc<Mutex<HashMap<u64, HashMap<Address, Account>>>> =
-            Default::default();
+        Self::default()
+    }
 
-        states_by_block
-            .lock()
-            .unwrap()
-            .insert(0, Default::default());
+    fn read(&self) -> Result<RwLockReadGuard<'_, Tables>, DatabaseError> {
+        self.tables.read().map_err(|_| DatabaseError::LockError)
+    }
 
-        Self {
-            states_by_block: states_by_block,
-            block_hash: Default::default(),
-            latest: Default::default(),
-        }
+    fn write(&self) -> Result<RwLockWriteGuard<'_, Tables>, DatabaseError> {
+        self.tables.write().map_err(|_| DatabaseError::LockError)
+    }
+
+    // Only one writer at a time, so the tables cannot change between preparing and applying.
+    fn lock_writer(&self) -> Result<MutexGuard<'_, ()>, DatabaseError> {
+        self.writer.lock().map_err(|_| DatabaseError::LockError)
     }
 
     pub fn set_balance(&mut self, address: Address, balance: U256) -> Result<(), DatabaseError> {
-        let mut block = self
-            .states_by_block
-            .lock()
-            .map_err(|_| DatabaseError::LockError)?;
-
-        let map = block
-            .get_mut(&self.latest)
-            .ok_or(DatabaseError::StateNotFoundError)?;
-
-        if let Some(account) = map.get_mut(&address) {
-            account.balance = balance;
-            Ok(())
-        } else {
-            map.insert(
-                address,
-                Account {
-                    nonce: 0,
-                    balance: balance,
-                },
-            );
-            Ok(())
+        let _writer = self.lock_writer()?;
+        let mut tables = self.write()?;
+        tables.plain_state.entry(address).or_default().balance = balance;
+        Ok(())
+    }
+
+    // Receipts below this block are pruned
+    pub(crate) fn receipts_pruned_below(&self) -> Result<u64, DatabaseError> {
+        Ok(self.read()?.receipts_pruned_below)
+    }
+
+    // Corrupts the indices like a partial write would.
+    #[cfg(test)]
+    pub(crate) fn remove_index_entries(&self, block: &BlockHash, tx: &TxHash) {
+        let mut tables = self.write().unwrap();
+        tables.header_numbers.remove(block);
+        tables.tx_index.remove(tx);
+    }
+
+    // Poisons the table lock like a writer that panicked.
+    #[cfg(test)]
+    pub(crate) fn poison(&self) {
+        let tables = self.tables.clone();
+        let _ = std::thread::spawn(move || {
+            let _tables = tables.write().unwrap();
+            panic!("writer panicked");
+        })
+        .join();
+    }
+
+    // Points the history of the account to a block whose changeset does not have it.
+    #[cfg(test)]
+    pub(crate) fn add_history_entry(&self, address: &Address, number: u64) {
+        let mut tables = self.write().unwrap();
+        tables
+            .account_history
+            .entry(address.clone())
+            .or_default()
+            .insert(number);
+    }
+}
+
+impl Tables {
+    fn check_state_available(&self, number: u64) -> Result<(), DatabaseError> {
+        if number < self.state_pruned_below || number > self.latest {
+            return Err(DatabaseError::StateNotFoundError);
+        }
+        Ok(())
+    }
+
+    // Every account changed after `number`, with its value at `number`.
+    // Applying them on top of the plain state gives the state at `number`.
+    fn reverted_accounts(
+        &self,
+        number: u64,
+    ) -> Result<BTreeMap<Address, Option<Account>>, DatabaseError> {
+        // Walking backwards, the oldest changeset of an account wins.
+        let mut reverted = BTreeMap::new();
+        for block_no in (number + 1..=self.latest).rev() {
+            let changeset = self
+                .account_changesets
+                .get(&block_no)
+                .ok_or(DatabaseError::ChangesetNotFound(block_no))?;
+            for (address, before) in changeset {
+                reverted.insert(address.clone(), *before);
+            }
+        }
+        Ok(reverted)
+    }
+
+    // Every storage slot changed after `number`, with its value at `number`.
+    fn reverted_storage(
+        &self,
+        number: u64,
+    ) -> Result<HashMap<(Address, StorageKey), Option<StorageValue>>, DatabaseError> {
+        let mut reverted = HashMap::new();
+        for block_no in (number + 1..=self.latest).rev() {
+            let changeset = self
+                .storage_changesets
+                .get(&block_no)
+                .ok_or(DatabaseError::ChangesetNotFound(block_no))?;
+            for (slot, before) in changeset {
+                reverted.insert(slot.clone(), *before);
+            }
+        }
+        Ok(reverted)
+    }
+
+    fn plain_slot(&self, address: &Address, key: &StorageKey) -> Option<StorageValue> {
+        self.plain_storage
+            .get(address)
+            .and_then(|slots| slots.get(key))
+            .copied()
+    }
+
+    fn set_plain_slot(&mut self, address: Address, key: StorageKey, value: Option<StorageValue>) {
+        match value.filter(|value| !value.is_zero()) {
+            Some(value) => {
+                self.plain_storage
+                    .entry(address)
+                    .or_default()
+                    .insert(key, value);
+            }
+            None => {
+                if let Some(slots) = self.plain_storage.get_mut(&address) {
+                    slots.remove(&key);
+                    if slots.is_empty() {
+                        self.plain_storage.remove(&address);
+                    }
+                }
+            }
         }
     }
 }
 
+// Value of `key` after the block `number`, read from the first change after the block.
+// Without a later change the value is still the current one.
+fn value_at_block<K: Eq + Hash, V: Copy>(
+    history: &HashMap<K, BTreeSet<u64>>,
+    changesets: &HashMap<u64, HashMap<K, Option<V>>>,
+    key: &K,
+    number: u64,
+    current: Option<V>,
+) -> Result<Option<V>, DatabaseError> {
+    let next_change = history
+        .get(key)
+        .and_then(|blocks| blocks.range(number + 1..).next());
+    match next_change {
+        Some(block_no) => changesets
+            .get(block_no)
+            .and_then(|changeset| changeset.get(key))
+            .copied()
+            .ok_or(DatabaseError::ChangesetNotFound(*block_no)),
+        None => Ok(current),
+    }
+}
+
 impl Database for InMemoryDB {
     fn basic(&self, address: &Address) -> Result<Option<Account>, DatabaseError> {
-        let binding = match self.states_by_block.lock() {
-            Ok(res) => res,
-            Err(_) => return Err(DatabaseError::LockError),
-        };
-        let state = match binding.get(&self.latest) {
-            Some(state) => state,
-            None => return Err(DatabaseError::StateNotFoundError),
-        };
-        let res = state.get(address);
-        Ok(res.copied())
+        Ok(self.read()?.plain_state.get(address).copied())
+    }
+
+    fn basic_at_block(
+        &self,
+        address: &Address,
+        number: u64,
+    ) -> Result<Option<Account>, DatabaseError> {
+        let tables = self.read()?;
+        tables.check_state_available(number)?;
+        value_at_block(
+            &tables.account_history,
+            &tables.account_changesets,
+            address,
+            number,
+            tables.plain_state.get(address).copied(),
+        )
     }
 
     fn block_hash(&self, number: u64) -> Result<Option<BlockHash>, DatabaseError> {
-        let binding = match self.block_hash.lock() {
-            Ok(res) => res,
-            Err(_) => return Err(DatabaseError::LockError),
-        };
-        let block_hash = binding.get(&number);
-        Ok(block_hash.copied())
+        Ok(self.read()?.block_hash.get(&number).copied())
     }
 
+    // A poisoned lock is not reported as head 0, which would look like an empty database.
     fn block_number(&self) -> u64 {
-        self.latest
+        self.read().expect("database lock poisoned").latest
     }
 
     fn copy_state_from_block_no(
         &self,
         number: u64,
     ) -> Result<HashMap<Address, Account>, DatabaseError> {
-        let states = self
-            .states_by_block
-            .lock()
-            .map_err(|_| DatabaseError::LockError)?;
-        let state = states
-            .get(&number)
-            .ok_or(DatabaseError::StateNotFoundError)?
-            .clone();
+        let tables = self.read()?;
+        tables.check_state_available(number)?;
+
+        let mut state: HashMap<_, _> = tables
+            .plain_state
+            .iter()
+            .map(|(address, account)| (address.clone(), *account))
+            .collect();
+        for (address, account) in tables.reverted_accounts(number)? {
+            match account {
+                Some(account) => state.insert(address, account),
+                None => state.remove(&address),
+            };
+        }
         Ok(state)
     }
+
+    fn header(&self, number: u64) -> Result<Option<Header>, DatabaseError> {
+        Ok(self.read()?.headers.get(&number).cloned())
+    }
+
+    fn body(&self, number: u64) -> Result<Option<BlockBody<TransactionSigned>>, DatabaseError> {
+        Ok(self.read()?.bodies.get(&number).cloned())
+    }
+
+    fn block_number_by_hash(&self, hash: &BlockHash) -> Result<Option<u64>, DatabaseError> {
+        Ok(self.read()?.header_numbers.get(hash).copied())
+    }
+
+    fn transaction_meta(&self, hash: &TxHash) -> Result<Option<TransactionMeta>, DatabaseError> {
+        Ok(self.read()?.tx_index.get(hash).copied())
+    }
+
+    fn receipts(&self, number: u64) -> Result<Option<Vec<Receipt>>, DatabaseError> {
+        Ok(self.read()?.receipts.get(&number).cloned())
+    }
+
+    fn earliest_state_block(&self) -> Result<u64, DatabaseError> {
+        Ok(self.read()?.state_pruned_below)
+    }
+
+    fn accounts_range(&self, start: &Address, limit: usize) -> Result<AccountsPage, DatabaseError> {
+        let tables = self.read()?;
+        let accounts = tables
+            .plain_state
+            .range(start..)
+            .take(limit.saturating_add(1))
+            .map(|(address, account)| (address.clone(), *account))
+            .collect();
+        Ok(AccountsPage::from_sorted(accounts, limit))
+    }
+
+    fn account_count(&self) -> Result<u64, DatabaseError> {
+        Ok(self.read()?.plain_state.len() as u64)
+    }
+
+    fn accounts_range_at_block(
+        &self,
+        number: u64,
+        start: &Address,
+        limit: usize,
+    ) -> Result<AccountsPage, DatabaseError> {
+        let tables = self.read()?;
+        tables.check_state_available(number)?;
+        let reverted = tables.reverted_accounts(number)?;
+
+        // Both sources are sorted and disjoint, so the first `limit + 1` of each are enough.
+        let take = limit.saturating_add(1);
+        let unchanged = tables
+            .plain_state
+            .range(start..)
+            .filter(|(address, _)| !reverted.contains_key(*address))
+            .take(take)
+            .map(|(address, account)| (address.clone(), *account));
+        let changed = reverted
+            .range(start..)
+            .filter_map(|(address, account)| account.map(|account| (address.clone(), account)))
+            .take(take);
+        let mut accounts: Vec<_> = unchanged.chain(changed).collect();
+        accounts.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+        accounts.truncate(take);
+        Ok(AccountsPage::from_sorted(accounts, limit))
+    }
+
+    fn account_count_at_block(&self, number: u64) -> Result<u64, DatabaseError> {
+        let tables = self.read()?;
+        tables.check_state_available(number)?;
+
+        let mut count = tables.plain_state.len() as u64;
+        for (address, account) in tables.reverted_accounts(number)? {
+            match (tables.plain_state.contains_key(&address), account.is_some()) {
+                (true, false) => count -= 1,
+                (false, true) => count += 1,
+                _ => {}
+            }
+        }
+        Ok(count)
+    }
+
+    fn storage(
+        &self,
+        address: &Address,
+        key: &StorageKey,
+    ) -> Result<Option<StorageValue>, DatabaseError> {
+        Ok(self.read()?.plain_slot(address, key))
+    }
+
+    fn storage_at_block(
+        &self,
+        address: &Address,
+        key: &StorageKey,
+        number: u64,
+    ) -> Result<Option<StorageValue>, DatabaseError> {
+        let tables = self.read()?;
+        tables.check_state_available(number)?;
+        let slot = (address.clone(), *key);
+        value_at_block(
+            &tables.storage_history,
+            &tables.storage_changesets,
+            &slot,
+            number,
+            tables.plain_slot(address, key),
+        )
+    }
+
+    fn copy_storage_from_block_no(
+        &self,
+        number: u64,
+    ) -> Result<HashMap<Address, AccountStorage>, DatabaseError> {
+        let tables = self.read()?;
+        tables.check_state_available(number)?;
+
+        let mut storage = tables.plain_storage.clone();
+        for ((address, key), value) in tables.reverted_storage(number)? {
+            match value {
+                Some(value) => {
+                    storage.entry(address).or_default().insert(key, value);
+                }
+                None => {
+                    if let Some(slots) = storage.get_mut(&address) {
+                        slots.remove(&key);
+                    }
+                }
+            }
+        }
+        storage.retain(|_, slots| !slots.is_empty());
+        Ok(storage)
+    }
+
+    fn account_changeset(&self, number: u64) -> Result<Option<AccountChangeset>, DatabaseError> {
+        Ok(self.read()?.account_changesets.get(&number).cloned())
+    }
+
+    fn storage_changeset(&self, number: u64) -> Result<Option<StorageChangeset>, DatabaseError> {
+        Ok(self.read()?.storage_changesets.get(&number).cloned())
+    }
+}
+
+impl DatabaseMut for InMemoryDB {
+    fn commit_block(&self, block: ExecutedBlock) -> Result<(), DatabaseError> {
+        let ExecutedBlock {
+            hash,
+            header,
+            body,
+            receipts,
+            state_diff,
+            storage_diff,
+        } = block;
+
+        let _writer = self.lock_writer()?;
+        let tables = self.read()?;
+        let number = header.number;
+
+        if number != tables.latest + 1 {
+            return Err(DatabaseError::InvalidBlockNumber(number));
+        }
+        if let Some(parent_hash) = tables.block_hash.get(&tables.latest)
+            && *parent_hash != header.previous_hash
+        {
+            return Err(DatabaseError::ParentHashMismatch(header.previous_hash));
+        }
+        if receipts.len() != body.transaction.len() {
+            return Err(DatabaseError::ReceiptsLengthMismatch(receipts.len()));
+        }
+
+        // Everything is computed under the read lock, so an error leaves the tables untouched
+        // and readers are only blocked while the block is applied.
+        let changeset: HashMap<_, _> = state_diff
+            .keys()
+            .map(|address| (address.clone(), tables.plain_state.get(address).copied()))
+            .collect();
+        let storage_changeset: HashMap<_, _> = storage_diff
+            .iter()
+            .flat_map(|(address, slots)| slots.keys().map(move |key| (address, key)))
+            .map(|(address, key)| ((address.clone(), *key), tables.plain_slot(address, key)))
+            .collect();
+
+        let tx_index: Vec<_> = body
+            .transaction
+            .iter()
+            .enumerate()
+            .map(|(index, tx)| {
+                let meta = TransactionMeta {
+                    block_hash: hash,
+                    block_number: number,
+                    index: index as u64,
+                };
+                (tx.hash(), meta)
+            })
+            .collect();
+        drop(tables);
+
+        let mut tables = self.write()?;
+        for (address, account) in state_diff {
+            tables
+                .account_history
+                .entry(address.clone())
+                .or_default()
+                .insert(number);
+            tables.plain_state.insert(address, account);
+        }
+        tables.account_changesets.insert(number, changeset);
+        for (address, slots) in storage_diff {
+            for (key, value) in slots {
+                tables
+                    .storage_history
+                    .entry((address.clone(), key))
+                    .or_default()
+                    .insert(number);
+                tables.set_plain_slot(address.clone(), key, Some(value));
+            }
+        }
+        tables.storage_changesets.insert(number, storage_changeset);
+        tables.headers.insert(number, header);
+        tables.bodies.insert(number, body);
+        tables.receipts.insert(number, receipts);
+        tables.tx_index.extend(tx_index);
+        tables.block_hash.insert(number, hash);
+        tables.header_numbers.insert(hash, number);
+        tables.latest = number;
+
+        Ok(())
+    }
+
+    fn unwind_to(&self, number: u64) -> Result<Vec<TransactionSigned>, DatabaseError> {
+        let _writer = self.lock_writer()?;
+        let tables = self.read()?;
+        let latest = tables.latest;
+
+        if number > latest {
+            return Err(DatabaseError::InvalidBlockNumber(number));
+        }
+
+        // Revert the head state block by block with the changesets.
+        let reverted = tables.reverted_accounts(number)?;
+        let reverted_storage = tables.reverted_storage(number)?;
+        drop(tables);
+
+        let mut tables = self.write()?;
+        for (address, account) in reverted {
+            match account {
+                Some(account) => tables.plain_state.insert(address, account),
+                None => tables.plain_state.remove(&address),
+            };
+        }
+        for ((address, key), value) in reverted_storage {
+            tables.set_plain_slot(address, key, value);
+        }
+
+        let mut removed = Vec::new();
+        for block_no in number + 1..=latest {
+            if let Some(changeset) = tables.account_changesets.remove(&block_no) {
+                remove_history(&mut tables.account_history, changeset.keys(), block_no);
+            }
+            if let Some(changeset) = tables.storage_changesets.remove(&block_no) {
+                remove_history(&mut tables.storage_history, changeset.keys(), block_no);
+            }
+            tables.headers.remove(&block_no);
+            tables.receipts.remove(&block_no);
+            if let Some(hash) = tables.block_hash.remove(&block_no) {
+                tables.header_numbers.remove(&hash);
+            }
+            if let Some(body) = tables.bodies.remove(&block_no) {
+                for tx in body.transaction {
+                    tables.tx_index.remove(&tx.hash());
+                    removed.push(tx);
+                }
+            }
+        }
+        tables.latest = number;
+
+        Ok(removed)
+    }
+
+    fn prune_state_history(&self, number: u64) -> Result<u64, DatabaseError> {
+        let _writer = self.lock_writer()?;
+        let mut tables = self.write()?;
+        let from = tables.state_pruned_below;
+        let to = number.min(tables.latest);
+        if to <= from {
+            return Ok(0);
+        }
+
+        // The changeset of a block is only needed to read the state below it.
+        for block_no in from + 1..=to {
+            if let Some(changeset) = tables.account_changesets.remove(&block_no) {
+                remove_history(&mut tables.account_history, changeset.keys(), block_no);
+            }
+            if let Some(changeset) = tables.storage_changesets.remove(&block_no) {
+                remove_history(&mut tables.storage_history, changeset.keys(), block_no);
+            }
+        }
+        tables.state_pruned_below = to;
+        Ok(to - from)
+    }
+
+    fn prune_receipts(&self, number: u64) -> Result<u64, DatabaseError> {
+        let _writer = self.lock_writer()?;
+        let mut tables = self.write()?;
+        let from = tables.receipts_pruned_below;
+        let to = number.min(tables.latest);
+        if to <= from {
+            return Ok(0);
+        }
+
+        for block_no in from..to {
+            tables.receipts.remove(&block_no);
+            let hashes: Vec<_> = tables
+                .bodies
+                .get(&block_no)
+                .map(|body| body.transaction.iter().map(|tx| tx.hash()).collect())
+                .unwrap_or_default();
+            for hash in hashes {
+                tables.tx_index.remove(&hash);
+            }
+        }
+        tables.receipts_pruned_below = to;
+        Ok(to - from)
+    }
+
+    fn init_from_state(
+        &self,
+        header: SealedHeader,
+        state: HashMap<Address, Account>,
+        storage: HashMap<Address, AccountStorage>,
+    ) -> Result<(), DatabaseError> {
+        let _writer = self.lock_writer()?;
+        let mut tables = self.write()?;
+        let is_empty = tables.latest == 0
+            && tables.headers.is_empty()
+            && tables.plain_state.is_empty()
+            && tables.plain_storage.is_empty();
+        if !is_empty {
+            return Err(DatabaseError::NotEmpty);
+        }
+
+        let hash = header.hash();
+        let header = header.into_header();
+        let number = header.number;
+
+        tables.plain_state = state.into_iter().collect();
+        for (address, slots) in storage {
+            for (key, value) in slots {
+                tables.set_plain_slot(address.clone(), key, Some(value));
+            }
+        }
+        if number == 0 {
+            tables.bodies.insert(number, BlockBody::default());
+            tables.receipts.insert(number, Vec::new());
+        }
+        tables.headers.insert(number, header);
+        tables.block_hash.insert(number, hash);
+        tables.header_numbers.insert(hash, number);
+        tables.latest = number;
+        tables.state_pruned_below = number;
+        tables.receipts_pruned_below = number;
+        Ok(())
+    }
+
+    fn rebuild_indices(&self) -> Result<u64, DatabaseError> {
+        let _writer = self.lock_writer()?;
+        let mut tables = self.write()?;
+        let header_numbers: HashMap<_, _> = tables
+            .block_hash
+            .iter()
+            .map(|(number, hash)| (*hash, *number))
+            .collect();
+        // pruned receipts took their transaction index entries with them
+        let mut tx_index = HashMap::new();
+        for (number, body) in &tables.bodies {
+            let Some(hash) = tables.block_hash.get(number) else {
+                continue;
+            };
+            if *number < tables.receipts_pruned_below {
+                continue;
+            }
+            for (index, tx) in body.transaction.iter().enumerate() {
+                let meta = TransactionMeta {
+                    block_hash: *hash,
+                    block_number: *number,
+                    index: index as u64,
+                };
+                tx_index.insert(tx.hash(), meta);
+            }
+        }
+
+        let changed = changed_entries(&tables.header_numbers, &header_numbers)
+            + changed_entries(&tables.tx_index, &tx_index);
+        tables.header_numbers = header_numbers;
+        tables.tx_index = tx_index;
+        Ok(changed)
+    }
+}
+
+// Number of entries that differ between the two maps
+fn changed_entries<K: Eq + Hash, V: PartialEq>(old: &HashMap<K, V>, new: &HashMap<K, V>) -> u64 {
+    let changed = old
+        .iter()
+        .filter(|(key, value)| new.get(key) != Some(value));
+    let added = new.keys().filter(|key| !old.contains_key(key));
+    (changed.count() + added.count()) as u64
+}
+
+// Drops `block_no` from the history of the given keys.
+fn remove_history<'a, K: Eq + Hash + 'a>(
+    history: &mut HashMap<K, BTreeSet<u64>>,
+    keys: impl Iterator<Item = &'a K>,
+    block_no: u64,
+) {
+    for key in keys {
+        if let Some(blocks) = history.get_mut(key) {
+            blocks.remove(&block_no);
+            if blocks.is_empty() {
+                history.remove(key);
+            }
+        }
+    }
 }
 
 impl Database for Arc<InMemoryDB> {
@@ -125,6 +859,14 @@ impl Database for Arc<InMemoryDB> {
         (**self).basic(address)
     }
 
+    fn basic_at_block(
+        &self,
+        address: &Address,
+        number: u64,
+    ) -> Result<Option<Account>, DatabaseError> {
+        (**self).basic_at_block(address, number)
+    }
+
     fn block_hash(&self, number: u64) -> Result<Option<BlockHash>, DatabaseError> {
         (**self).block_hash(number)
     }
@@ -139,4 +881,562 @@ impl Database for Arc<InMemoryDB> {
     ) -> Result<HashMap<Address, Account>, DatabaseError> {
         (**self).copy_state_from_block_no(number)
     }
+
+    fn header(&self, number: u64) -> Result<Option<Header>, DatabaseError> {
+        (**self).header(number)
+    }
+
+    fn body(&self, number: u64) -> Result<Option<BlockBody<TransactionSigned>>, DatabaseError> {
+        (**self).body(number)
+    }
+
+    fn block_number_by_hash(&self, hash: &BlockHash) -> Result<Option<u64>, DatabaseError> {
+        (**self).block_number_by_hash(hash)
+    }
+
+    fn transaction_meta(&self, hash: &TxHash) -> Result<Option<TransactionMeta>, DatabaseError> {
+        (**self).transaction_meta(hash)
+    }
+
+    fn receipts(&self, number: u64) -> Result<Option<Vec<Receipt>>, DatabaseError> {
+        (**self).receipts(number)
+    }
+
+    fn earliest_state_block(&self) -> Result<u64, DatabaseError> {
+        (**self).earliest_state_block()
+    }
+
+    fn accounts_range(&self, start: &Address, limit: usize) -> Result<AccountsPage, DatabaseError> {
+        (**self).accounts_range(start, limit)
+    }
+
+    fn account_count(&self) -> Result<u64, DatabaseError> {
+        (**self).account_count()
+    }
+
+    fn accounts_range_at_block(
+        &self,
+        number: u64,
+        start: &Address,
+        limit: usize,
+    ) -> Result<AccountsPage, DatabaseError> {
+        (**self).accounts_range_at_block(number, start, limit)
+    }
+
+    fn account_count_at_block(&self, number: u64) -> Result<u64, DatabaseError> {
+        (**self).account_count_at_block(number)
+    }
+
+    fn storage(
+        &self,
+        address: &Address,
+        key: &StorageKey,
+    ) -> Result<Option<StorageValue>, DatabaseError> {
+        (**self).storage(address, key)
+    }
+
+    fn storage_at_block(
+        &self,
+        address: &Address,
+        key: &StorageKey,
+        number: u64,
+    ) -> Result<Option<StorageValue>, DatabaseError> {
+        (**self).storage_at_block(address, key, number)
+    }
+
+    fn copy_storage_from_block_no(
+        &self,
+        number: u64,
+    ) -> Result<HashMap<Address, AccountStorage>, DatabaseError> {
+        (**self).copy_storage_from_block_no(number)
+    }
+
+    fn account_changeset(&self, number: u64) -> Result<Option<AccountChangeset>, DatabaseError> {
+        (**self).account_changeset(number)
+    }
+
+    fn storage_changeset(&self, number: u64) -> Result<Option<StorageChangeset>, DatabaseError> {
+        (**self).storage_changeset(number)
+    }
+}
+
+impl DatabaseMut for Arc<InMemoryDB> {
+    fn commit_block(&self, block: ExecutedBlock) -> Result<(), DatabaseError> {
+        (**self).commit_block(block)
+    }
+
+    fn unwind_to(&self, number: u64) -> Result<Vec<TransactionSigned>, DatabaseError> {
+        (**self).unwind_to(number)
+    }
+
+    fn prune_state_history(&self, number: u64) -> Result<u64, DatabaseError> {
+        (**self).prune_state_history(number)
+    }
+
+    fn prune_receipts(&self, number: u64) -> Result<u64, DatabaseError> {
+        (**self).prune_receipts(number)
+    }
+
+    fn init_from_state(
+        &self,
+        header: SealedHeader,
+        state: HashMap<Address, Account>,
+        storage: HashMap<Address, AccountStorage>,
+    ) -> Result<(), DatabaseError> {
+        (**self).init_from_state(header, state, storage)
+    }
+
+    fn rebuild_indices(&self) -> Result<u64, DatabaseError> {
+        (**self).rebuild_indices()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use primitives::{signature::Signature, types::B256};
+    use proptest::prelude::*;
+    use transaction::{PintTx, signed::Signed, transaction::TxEnvelope};
+
+    use super::*;
+
+    fn block_on(parent: BlockHash, number: u64, diff: &[(Address, Account)]) -> ExecutedBlock {
+        let header = Header {
+            previous_hash: parent,
+            number,
+            ..Default::default()
+        };
+        ExecutedBlock::new(
+            header,
+            BlockBody::default(),
+            Vec::new(),
+            diff.iter().cloned().collect(),
+        )
+    }
+
+    #[test]
+    #[should_panic(expected = "database lock poisoned")]
+    fn test_block_number_panics_on_a_poisoned_lock() {
+        let db = InMemoryDB::new();
+        let tables = db.tables.clone();
+        let _ = std::thread::spawn(move || {
+            let _guard = tables.write().unwrap();
+            panic!("writer failed");
+        })
+        .join();
+        db.block_number();
+    }
+
+    #[test]
+    fn test_commit_block_moves_head_for_clones() {
+        let db = InMemoryDB::new();
+        let reader = db.clone();
+        let address = Address::random();
+        let account = Account {
+            nonce: 1,
+            balance: U256::from(5),
+        };
+
+        let block = block_on(BlockHash::ZERO, 1, &[(address.clone(), account)]);
+        let hash = block.hash;
+        db.commit_block(block).unwrap();
+
+        assert_eq!(reader.block_number(), 1);
+        assert_eq!(reader.block_hash(1).unwrap(), Some(hash));
+        assert_eq!(reader.basic(&address).unwrap(), Some(account));
+        assert_eq!(
+            reader.copy_state_from_block_no(0).unwrap().get(&address),
+            None
+        );
+    }
+
+    #[test]
+    fn test_commit_block_rejects_invalid_block() {
+        let db = InMemoryDB::new();
+        let address = Address::random();
+        let first = block_on(BlockHash::ZERO, 1, &[]);
+        db.commit_block(first).unwrap();
+
+        let gap = block_on(BlockHash::ZERO, 3, &[(address.clone(), Account::default())]);
+        assert!(matches!(
+            db.commit_block(gap),
+            Err(DatabaseError::InvalidBlockNumber(3))
+        ));
+
+        let wrong_parent = block_on(
+            BlockHash::random(),
+            2,
+            &[(address.clone(), Account::default())],
+        );
+        assert!(matches!(
+            db.commit_block(wrong_parent),
+            Err(DatabaseError::ParentHashMismatch(_))
+        ));
+
+        // nothing of the rejected blocks is visible
+        assert_eq!(db.block_number(), 1);
+        assert_eq!(db.block_hash(2).unwrap(), None);
+        assert_eq!(db.basic(&address).unwrap(), None);
+    }
+
+    const ACCOUNTS: usize = 4;
+
+    /// Transfers between a few accounts, grouped into blocks
+    fn arb_blocks() -> impl Strategy<Value = Vec<Vec<(usize, usize, u64)>>> {
+        prop::collection::vec(
+            prop::collection::vec((0..ACCOUNTS, 0..ACCOUNTS, 0..50u64), 0..4),
+            1..8,
+        )
+    }
+
+    // Transfer with a placeholder signature and a hash unique to its position in the chain,
+    // the database does not verify either.
+    fn transfer(number: u64, index: usize, to: &Address, nonce: u64, value: U256) -> TxEnvelope {
+        let tx = PintTx {
+            chain_id: 0,
+            nonce,
+            to: to.clone(),
+            fee: 0,
+            value,
+        };
+        let hash = B256::from(U256::from(number) << 32 | U256::from(index));
+        let signature = Signature::from_bytes_and_parity(&[0; 64], false);
+        TxEnvelope::Pint(Signed::new(tx, signature, hash))
+    }
+
+    /// Executes the transfers on top of the db head and commits each block with a transaction
+    /// and receipt for every executed transfer.
+    fn execute_and_commit(
+        db: &InMemoryDB,
+        addresses: &[Address],
+        blocks: &[Vec<(usize, usize, u64)>],
+    ) {
+        for transfers in blocks {
+            let number = db.block_number() + 1;
+            let parent = db.block_hash(number - 1).unwrap().unwrap_or_default();
+            let mut diff: HashMap<Address, Account> = HashMap::new();
+            let mut transaction = Vec::new();
+            for (from, to, value) in transfers {
+                let value = U256::from(*value);
+                let mut sender = diff
+                    .get(&addresses[*from])
+                    .copied()
+                    .or(db.basic(&addresses[*from]).unwrap())
+                    .unwrap_or_default();
+                if sender.balance < value {
+                    continue;
+                }
+                transaction.push(transfer(
+                    number,
+                    transaction.len(),
+                    &addresses[*to],
+                    sender.nonce,
+                    value,
+                ));
+                sender.balance -= value;
+                sender.nonce += 1;
+                diff.insert(addresses[*from].clone(), sender);
+
+                let mut receiver = diff
+                    .get(&addresses[*to])
+                    .copied()
+                    .or(db.basic(&addresses[*to]).unwrap())
+                    .unwrap_or_default();
+                receiver.balance += value;
+                diff.insert(addresses[*to].clone(), receiver);
+            }
+            let receipts = transaction
+                .iter()
+                .map(|tx| Receipt {
+                    tx_hash: tx.hash(),
+                    success: true,
+                    ..Default::default()
+                })
+                .collect();
+            let header = Header {
+                previous_hash: parent,
+                number,
+                ..Default::default()
+            };
+            let block = ExecutedBlock::new(header, BlockBody { transaction }, receipts, diff);
+            db.commit_block(block).unwrap();
+        }
+    }
+
+    fn genesis_db(addresses: &[Address]) -> InMemoryDB {
+        let mut db = InMemoryDB::new();
+        // only the first account is funded at genesis
+        db.set_balance(addresses[0].clone(), U256::from(100))
+            .unwrap();
+        db
+    }
+
+    proptest! {
+        #[test]
+        fn test_unwind_matches_reexecution(blocks in arb_blocks(), target in 0..8u64) {
+            let addresses: Vec<_> = (0..ACCOUNTS).map(|_| Address::random()).collect();
+            let target = target.min(blocks.len() as u64);
+
+            let unwound = genesis_db(&addresses);
+            execute_and_commit(&unwound, &addresses, &blocks);
+            let expected: Vec<_> = (target + 1..=blocks.len() as u64)
+                .flat_map(|number| unwound.body(number).unwrap().unwrap().transaction)
+                .map(|tx| tx.hash())
+                .collect();
+            let removed = unwound.unwind_to(target).unwrap();
+            // the transactions of the removed blocks are returned oldest first
+            prop_assert_eq!(removed.iter().map(|tx| tx.hash()).collect::<Vec<_>>(), expected.clone());
+            for hash in &expected {
+                prop_assert_eq!(unwound.transaction_meta(hash).unwrap(), None);
+            }
+
+            let reexecuted = genesis_db(&addresses);
+            execute_and_commit(&reexecuted, &addresses, &blocks[..target as usize]);
+
+            prop_assert_eq!(unwound.block_number(), reexecuted.block_number());
+            prop_assert_eq!(
+                unwound.copy_state_from_block_no(target).unwrap(),
+                reexecuted.copy_state_from_block_no(target).unwrap()
+            );
+            for number in 0..=blocks.len() as u64 + 1 {
+                prop_assert_eq!(unwound.block_hash(number).unwrap(), reexecuted.block_hash(number).unwrap());
+                prop_assert_eq!(unwound.header(number).unwrap(), reexecuted.header(number).unwrap());
+                prop_assert_eq!(unwound.receipts(number).unwrap(), reexecuted.receipts(number).unwrap());
+                let hashes = |db: &InMemoryDB| {
+                    db.body(number)
+                        .unwrap()
+                        .map(|body| body.transaction.iter().map(|tx| tx.hash()).collect::<Vec<_>>())
+                };
+                prop_assert_eq!(hashes(&unwound), hashes(&reexecuted));
+                for hash in hashes(&reexecuted).unwrap_or_default() {
+                    prop_assert_eq!(
+                        unwound.transaction_meta(&hash).unwrap(),
+                        reexecuted.transaction_meta(&hash).unwrap()
+                    );
+                }
+            }
+            for address in &addresses {
+                prop_assert_eq!(unwound.basic(address).unwrap(), reexecuted.basic(address).unwrap());
+            }
+
+            // the chain can grow again from the unwound head
+            execute_and_commit(&unwound, &addresses, &blocks[target as usize..]);
+            prop_assert_eq!(unwound.block_number(), blocks.len() as u64);
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn test_history_matches_reexecution(blocks in arb_blocks(), page_size in 1..4usize) {
+            let addresses: Vec<_> = (0..ACCOUNTS).map(|_| Address::random()).collect();
+            let db = genesis_db(&addresses);
+            execute_and_commit(&db, &addresses, &blocks);
+
+            for number in 0..=blocks.len() as u64 {
+                let reexecuted = genesis_db(&addresses);
+                execute_and_commit(&reexecuted, &addresses, &blocks[..number as usize]);
+                for address in &addresses {
+                    prop_assert_eq!(
+                        db.basic_at_block(address, number).unwrap(),
+                        reexecuted.basic(address).unwrap()
+                    );
+                }
+                prop_assert_eq!(
+                    db.copy_state_from_block_no(number).unwrap(),
+                    reexecuted.copy_state_from_block_no(number).unwrap()
+                );
+
+                let mut expected: Vec<_> = reexecuted.copy_state_from_block_no(number).unwrap().into_iter().collect();
+                expected.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+                prop_assert_eq!(db.account_count_at_block(number).unwrap(), expected.len() as u64);
+                prop_assert_eq!(all_pages(&db, number, page_size), expected);
+            }
+        }
+    }
+
+    /// Walks the state at the block page by page
+    fn all_pages(db: &InMemoryDB, number: u64, limit: usize) -> Vec<(Address, Account)> {
+        let mut accounts = Vec::new();
+        let mut start = Some(Address::default());
+        while let Some(address) = start {
+            let page = db.accounts_range_at_block(number, &address, limit).unwrap();
+            assert!(page.accounts.len() <= limit);
+            accounts.extend(page.accounts);
+            start = page.next;
+        }
+        accounts
+    }
+
+    #[test]
+    fn test_accounts_range_latest() {
+        let addresses: Vec<_> = (0..5).map(|_| Address::random()).collect();
+        let mut db = InMemoryDB::new();
+        for address in &addresses {
+            db.set_balance(address.clone(), U256::from(1)).unwrap();
+        }
+        let mut sorted = addresses.clone();
+        sorted.sort();
+
+        assert_eq!(db.account_count().unwrap(), 5);
+        let first = db.accounts_range(&Address::default(), 2).unwrap();
+        assert_eq!(first.accounts.len(), 2);
+        assert_eq!(first.accounts[0].0, sorted[0]);
+        assert_eq!(first.next, Some(sorted[2].clone()));
+
+        let last = db.accounts_range(&sorted[3], 2).unwrap();
+        assert_eq!(last.accounts.len(), 2);
+        assert_eq!(last.next, None);
+        assert_eq!(
+            db.accounts_range(&sorted[0], 0).unwrap().next,
+            Some(sorted[0].clone())
+        );
+    }
+
+    #[test]
+    fn test_basic_at_block_across_blocks() {
+        let db = InMemoryDB::new();
+        let address = Address::random();
+        let other = Address::random();
+
+        // the account changes every third block only
+        let mut parent = BlockHash::ZERO;
+        for number in 1..=30u64 {
+            let mut diff = vec![(other.clone(), Account::default())];
+            if number % 3 == 0 {
+                let account = Account {
+                    nonce: number / 3,
+                    balance: U256::from(number),
+                };
+                diff.push((address.clone(), account));
+            }
+            let block = block_on(parent, number, &diff);
+            parent = block.hash;
+            db.commit_block(block).unwrap();
+        }
+
+        for number in 0..=30u64 {
+            let expected = (number >= 3).then(|| Account {
+                nonce: number / 3,
+                balance: U256::from(number - number % 3),
+            });
+            assert_eq!(db.basic_at_block(&address, number).unwrap(), expected);
+        }
+        assert!(matches!(
+            db.basic_at_block(&address, 31),
+            Err(DatabaseError::StateNotFoundError)
+        ));
+
+        db.prune_state_history(10).unwrap();
+        assert!(matches!(
+            db.basic_at_block(&address, 9),
+            Err(DatabaseError::StateNotFoundError)
+        ));
+        assert_eq!(
+            db.basic_at_block(&address, 10)
+                .unwrap()
+                .map(|acc| acc.nonce),
+            Some(3)
+        );
+
+        db.unwind_to(20).unwrap();
+        assert_eq!(db.basic(&address).unwrap().map(|acc| acc.nonce), Some(6));
+        assert_eq!(
+            db.basic_at_block(&address, 17)
+                .unwrap()
+                .map(|acc| acc.nonce),
+            Some(5)
+        );
+    }
+
+    #[test]
+    fn test_storage_history_and_unwind() {
+        let db = InMemoryDB::new();
+        let address = Address::random();
+        let key = StorageKey::random();
+
+        // the slot is written in block 1, changed in block 3 and cleared in block 5
+        let writes = [(1, 10u64), (3, 30), (5, 0)];
+        let mut parent = BlockHash::ZERO;
+        for number in 1..=6u64 {
+            let mut block = block_on(parent, number, &[(address.clone(), Account::default())]);
+            if let Some((_, value)) = writes.iter().find(|(at, _)| *at == number) {
+                let slots = AccountStorage::from([(key, StorageValue::from(*value))]);
+                block = block.with_storage_diff(HashMap::from([(address.clone(), slots)]));
+            }
+            parent = block.hash;
+            db.commit_block(block).unwrap();
+        }
+
+        let expected = [None, Some(10), Some(10), Some(30), Some(30), None, None];
+        for (number, value) in expected.iter().enumerate() {
+            assert_eq!(
+                db.storage_at_block(&address, &key, number as u64).unwrap(),
+                value.map(StorageValue::from)
+            );
+        }
+        assert_eq!(db.storage(&address, &key).unwrap(), None);
+        assert!(db.copy_storage_from_block_no(6).unwrap().is_empty());
+        assert_eq!(
+            db.copy_storage_from_block_no(4).unwrap()[&address][&key],
+            StorageValue::from(30)
+        );
+
+        db.unwind_to(3).unwrap();
+        assert_eq!(
+            db.storage(&address, &key).unwrap(),
+            Some(StorageValue::from(30))
+        );
+        db.prune_state_history(2).unwrap();
+        assert!(db.storage_at_block(&address, &key, 1).is_err());
+        assert_eq!(
+            db.storage_at_block(&address, &key, 2).unwrap(),
+            Some(StorageValue::from(10))
+        );
+    }
+
+    #[test]
+    fn test_concurrent_readers_see_consistent_state() {
+        let addresses = [Address::random(), Address::random()];
+        let db = genesis_db(&addresses);
+        let total = U256::from(100);
+
+        let readers: Vec<_> = (0..4)
+            .map(|_| {
+                let (db, addresses) = (db.clone(), addresses.clone());
+                std::thread::spawn(move || {
+                    let mut last = 0;
+                    while last < 50 {
+                        let number = db.block_number();
+                        assert!(number >= last);
+                        let sum: U256 = addresses
+                            .iter()
+                            .filter_map(|address| db.basic_at_block(address, number).unwrap())
+                            .map(|account| account.balance)
+                            .sum();
+                        assert_eq!(sum, total);
+                        last = number;
+                    }
+                })
+            })
+            .collect();
+
+        // move one unit back and forth between the two accounts
+        let blocks: Vec<_> = (0..50).map(|i| vec![(i % 2, (i + 1) % 2, 1)]).collect();
+        execute_and_commit(&db, &addresses, &blocks);
+        for reader in readers {
+            reader.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_unwind_above_head_is_rejected() {
+        let db = InMemoryDB::new();
+        db.commit_block(block_on(BlockHash::ZERO, 1, &[])).unwrap();
+
+        assert!(matches!(
+            db.unwind_to(2),
+            Err(DatabaseError::InvalidBlockNumber(2))
+        ));
+        assert!(db.unwind_to(1).unwrap().is_empty());
+        assert_eq!(db.block_number(), 1);
+    }
 }
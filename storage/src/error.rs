@@ -1,9 +1,16 @@
-use std::fmt;
+use std::{fmt, io};
+
+use primitives::types::{B256, BlockHash};
+
+use crate::migration::SCHEMA_VERSION;
 
 #[derive(Debug)]
 pub enum ProviderError {
     InvalidSomething,
     DatabaseError(DatabaseError),
+    BlockHashNotFound(BlockHash),
+    // state history of the block was removed by the pruner
+    StatePruned(u64),
 }
 
 impl fmt::Display for ProviderError {
@@ -11,6 +18,8 @@ impl fmt::Display for ProviderError {
         match self {
             ProviderError::InvalidSomething => write!(f, "Invalid something"),
             ProviderError::DatabaseError(_) => write!(f, "Database Error"),
+            ProviderError::BlockHashNotFound(hash) => write!(f, "Block hash not found: {}", hash),
+            ProviderError::StatePruned(number) => write!(f, "State of block {} is pruned", number),
         }
     }
 }
@@ -27,4 +36,129 @@ impl std::error::Error for ProviderError {}
 pub enum DatabaseError {
     LockError,
     StateNotFoundError,
+    // committed block is not the child of the canonical head
+    InvalidBlockNumber(u64),
+    ParentHashMismatch(BlockHash),
+    // number of receipts differs from the number of transactions
+    ReceiptsLengthMismatch(usize),
+    ChangesetNotFound(u64),
+    // database already has a chain
+    NotEmpty,
+    // the write-ahead log could not be read or written
+    Io(io::Error),
+    // database was written by a newer node
+    SchemaVersionTooNew(u32),
+    // no migration upgrades the schema version
+    UnsupportedSchemaVersion(u32),
+    // file is neither a log with a header nor a log of version 1
+    UnknownLogFormat,
+    // log of an older schema version was opened without migrating it
+    SchemaMigrationRequired(u32),
+    // record of the write-ahead log at the offset is damaged and records follow it
+    CorruptLog {
+        offset: u64,
+    },
+    // a failed write of the write-ahead log could not be undone, the log must be reopened
+    LogPoisoned,
+    // database was initialized with another genesis block
+    GenesisMismatch {
+        stored: BlockHash,
+        configured: BlockHash,
+    },
+}
+
+impl fmt::Display for DatabaseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DatabaseError::SchemaVersionTooNew(version) => write!(
+                f,
+                "Database schema version {} is newer than the supported version {}, upgrade the node",
+                version, SCHEMA_VERSION
+            ),
+            DatabaseError::UnsupportedSchemaVersion(version) => {
+                write!(f, "Database schema version {} cannot be migrated", version)
+            }
+            DatabaseError::SchemaMigrationRequired(version) => write!(
+                f,
+                "Database schema version {} must be migrated to version {}",
+                version, SCHEMA_VERSION
+            ),
+            DatabaseError::GenesisMismatch { stored, configured } => write!(
+                f,
+                "Stored genesis {} does not match the configured genesis {}",
+                stored, configured
+            ),
+            DatabaseError::UnknownLogFormat => write!(f, "File is not a write-ahead log"),
+            DatabaseError::CorruptLog { offset } => write!(
+                f,
+                "Write-ahead log is corrupted at offset {}, restore it from a backup",
+                offset
+            ),
+            DatabaseError::LogPoisoned => {
+                write!(
+                    f,
+                    "Write-ahead log failed to undo a write, reopen the database"
+                )
+            }
+            DatabaseError::Io(e) => write!(f, "Database IO error: {}", e),
+            other => write!(f, "Database error: {:?}", other),
+        }
+    }
+}
+
+impl std::error::Error for DatabaseError {}
+
+impl From<io::Error> for DatabaseError {
+    fn from(value: io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    Io(io::Error),
+    Database(DatabaseError),
+    InvalidMagic,
+    UnsupportedVersion(u8),
+    HeaderNotFound(u64),
+    BlockHashMismatch(BlockHash),
+    ChecksumMismatch(u64),
+    UnsortedAccounts,
+    AccountCountMismatch(u64),
+    StateRootMismatch { expected: B256, got: B256 },
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotError::Io(e) => write!(f, "Snapshot io error: {}", e),
+            SnapshotError::Database(e) => write!(f, "Database error: {:?}", e),
+            SnapshotError::InvalidMagic => write!(f, "Not a snapshot file"),
+            SnapshotError::UnsupportedVersion(v) => {
+                write!(f, "Unsupported snapshot version: {}", v)
+            }
+            SnapshotError::HeaderNotFound(n) => write!(f, "Header not found: {}", n),
+            SnapshotError::BlockHashMismatch(h) => write!(f, "Header does not hash to {}", h),
+            SnapshotError::ChecksumMismatch(i) => write!(f, "Invalid checksum of chunk {}", i),
+            SnapshotError::UnsortedAccounts => write!(f, "Accounts are not sorted by address"),
+            SnapshotError::AccountCountMismatch(n) => write!(f, "Unexpected account count: {}", n),
+            SnapshotError::StateRootMismatch { expected, got } => {
+                write!(f, "State root mismatch: expected {}, got {}", expected, got)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+impl From<io::Error> for SnapshotError {
+    fn from(value: io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl From<DatabaseError> for SnapshotError {
+    fn from(value: DatabaseError) -> Self {
+        Self::Database(value)
+    }
 }
@@ -0,0 +1,184 @@
+//! Genesis block of the chain.
+//! The genesis spec becomes block 0 with an empty body, and its hash identifies the chain a
+//! database belongs to.
+use std::collections::{BTreeMap, HashMap};
+
+use primitives::{
+    account::{Account, AccountStorage},
+    block::header::{Header, SealedHeader},
+    proofs::calculate_state_root,
+    types::{Address, B256, BlockHash, U256},
+};
+
+use crate::{db::DatabaseMut, error::DatabaseError};
+
+/// Allocation of a single account at genesis
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GenesisAccount {
+    pub nonce: u64,
+    pub balance: U256,
+    pub storage: AccountStorage,
+}
+
+/// Genesis spec of the chain
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Genesis {
+    pub timestamp: u64,
+    pub alloc: BTreeMap<Address, GenesisAccount>,
+}
+
+impl Genesis {
+    pub fn with_timestamp(mut self, timestamp: u64) -> Self {
+        self.timestamp = timestamp;
+        self
+    }
+
+    pub fn with_account(mut self, address: Address, account: GenesisAccount) -> Self {
+        self.alloc.insert(address, account);
+        self
+    }
+
+    /// Header of block 0, committing to the allocations
+    pub fn header(&self) -> SealedHeader {
+        let (state, storage) = self.state();
+        SealedHeader::seal_slow(Header {
+            previous_hash: B256::ZERO,
+            number: 0,
+            transaction_root: B256::ZERO,
+            state_root: calculate_state_root(&state, &storage),
+            receipts_root: B256::ZERO,
+            timestamp: self.timestamp,
+            beneficiary: Address::default(),
+        })
+    }
+
+    fn state(&self) -> (HashMap<Address, Account>, HashMap<Address, AccountStorage>) {
+        let mut state = HashMap::new();
+        let mut storage = HashMap::new();
+        for (address, account) in &self.alloc {
+            let info = Account {
+                nonce: account.nonce,
+                balance: account.balance,
+            };
+            state.insert(address.clone(), info);
+            if !account.storage.is_empty() {
+                storage.insert(address.clone(), account.storage.clone());
+            }
+        }
+        (state, storage)
+    }
+}
+
+/// Writes the genesis block into an empty database.
+/// A database that already has a genesis block must have been initialized with the same spec,
+/// otherwise it belongs to another chain and [DatabaseError::GenesisMismatch] is returned.
+/// Returns the genesis hash.
+pub fn init_genesis<DB: DatabaseMut>(
+    db: &DB,
+    genesis: &Genesis,
+) -> Result<BlockHash, DatabaseError> {
+    let header = genesis.header();
+    let configured = header.hash();
+    if let Some(stored) = db.block_hash(0)? {
+        if stored != configured {
+            return Err(DatabaseError::GenesisMismatch { stored, configured });
+        }
+        return Ok(stored);
+    }
+
+    let (state, storage) = genesis.state();
+    db.init_from_state(header, state, storage)?;
+    Ok(configured)
+}
+
+#[cfg(test)]
+mod tests {
+    use primitives::types::StorageKey;
+
+    use super::*;
+    use crate::{
+        PintStateProviderFactory,
+        db::{Database, InMemoryDB},
+        traits::HeaderProvider,
+        wal::WalDB,
+    };
+
+    fn genesis() -> Genesis {
+        let storage = AccountStorage::from([(StorageKey::with_last_byte(1), U256::from(7))]);
+        Genesis::default()
+            .with_timestamp(1_700_000_000)
+            .with_account(
+                Address::from_byte([1; 20]),
+                GenesisAccount {
+                    balance: U256::from(1_000),
+                    ..Default::default()
+                },
+            )
+            .with_account(
+                Address::from_byte([2; 20]),
+                GenesisAccount {
+                    nonce: 1,
+                    balance: U256::from(5),
+                    storage,
+                },
+            )
+    }
+
+    #[test]
+    fn test_init_writes_block_zero() {
+        let db = InMemoryDB::new();
+        let hash = init_genesis(&db, &genesis()).unwrap();
+
+        assert_eq!(db.block_hash(0).unwrap(), Some(hash));
+        assert_eq!(db.block_number(), 0);
+        assert!(db.body(0).unwrap().unwrap().transaction.is_empty());
+        assert!(db.receipts(0).unwrap().unwrap().is_empty());
+        assert_eq!(
+            db.basic(&Address::from_byte([1; 20]))
+                .unwrap()
+                .unwrap()
+                .balance,
+            U256::from(1_000)
+        );
+        assert_eq!(
+            db.storage(&Address::from_byte([2; 20]), &StorageKey::with_last_byte(1))
+                .unwrap(),
+            Some(U256::from(7))
+        );
+
+        let header = PintStateProviderFactory::new(db.clone())
+            .latest_header()
+            .unwrap()
+            .unwrap();
+        assert_eq!(header.hash(), hash);
+        let state = db.copy_state_from_block_no(0).unwrap();
+        let storage = db.copy_storage_from_block_no(0).unwrap();
+        assert_eq!(
+            header.header().state_root,
+            calculate_state_root(&state, &storage)
+        );
+    }
+
+    #[test]
+    fn test_init_is_idempotent() {
+        let db = InMemoryDB::new();
+        let hash = init_genesis(&db, &genesis()).unwrap();
+        assert_eq!(init_genesis(&db, &genesis()).unwrap(), hash);
+    }
+
+    #[test]
+    fn test_restart_with_other_genesis_fails() {
+        let path = std::env::temp_dir().join(format!("pint-genesis-{}", B256::random()));
+        let hash = init_genesis(&WalDB::open(&path).unwrap(), &genesis()).unwrap();
+
+        let db = WalDB::open(&path).unwrap();
+        assert_eq!(init_genesis(&db, &genesis()).unwrap(), hash);
+        let other = genesis().with_timestamp(0);
+        assert!(matches!(
+            init_genesis(&db, &other),
+            Err(DatabaseError::GenesisMismatch { stored, configured })
+                if stored == hash && configured == other.header().hash()
+        ));
+        std::fs::remove_file(path).unwrap();
+    }
+}
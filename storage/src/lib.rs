@@ -1,15 +1,36 @@
+pub mod cache;
+pub mod check;
 pub mod db;
 pub mod error;
+pub mod genesis;
+pub mod migration;
+pub mod prune;
+pub mod snapshot;
 pub mod traits;
+pub mod wal;
 
 use std::collections::HashMap;
 
-use primitives::{account::Account, types::BlockHash};
+use primitives::{
+    account::Account,
+    block::{
+        Block,
+        body::{BlockBody, SealedBlock},
+        header::{Header, SealedHeader},
+    },
+    proofs::calculate_state_root,
+    receipt::Receipt,
+    types::{B256, BlockHash, StorageKey, StorageValue, TxHash},
+};
+use transaction::TransactionSigned;
 
 use crate::{
-    db::Database,
+    db::{AccountsPage, Database, TransactionMeta},
     error::ProviderError,
-    traits::{AccountReader, ProviderResult, StateProvider, StateProviderFactory},
+    traits::{
+        AccountReader, BlockReader, HeaderProvider, ProviderResult, ReceiptProvider, StateProvider,
+        StateProviderFactory, TransactionsProvider,
+    },
 };
 
 /// State which is created by StateProviderFactory
@@ -19,6 +40,14 @@ pub struct PintStateProvider<DB> {
 }
 
 impl<DB: Database> StateProvider for PintStateProvider<DB> {
+    fn account_storage(
+        &self,
+        addr: &primitives::types::Address,
+        key: &StorageKey,
+    ) -> ProviderResult<Option<StorageValue>> {
+        Ok(self.db.storage_at_block(addr, key, self.block_no)?)
+    }
+
     fn prepare_execute(&mut self) -> ProviderResult<HashMap<primitives::types::Address, Account>> {
         let res = match self.db.copy_state_from_block_no(self.block_no) {
             Ok(res) => res,
@@ -26,6 +55,16 @@ impl<DB: Database> StateProvider for PintStateProvider<DB> {
         };
         Ok(res)
     }
+
+    fn state_root(
+        &self,
+        diff: &HashMap<primitives::types::Address, Account>,
+    ) -> ProviderResult<B256> {
+        let mut state = self.db.copy_state_from_block_no(self.block_no)?;
+        state.extend(diff.iter().map(|(address, account)| (address.clone(), *account)));
+        let storage = self.db.copy_storage_from_block_no(self.block_no)?;
+        Ok(calculate_state_root(&state, &storage))
+    }
 }
 
 impl<DB: Database> AccountReader for PintStateProvider<DB> {
@@ -33,7 +72,21 @@ impl<DB: Database> AccountReader for PintStateProvider<DB> {
         &self,
         address: &primitives::types::Address,
     ) -> Result<Option<Account>, ProviderError> {
-        Ok(self.db.basic(address)?)
+        Ok(self.db.basic_at_block(address, self.block_no)?)
+    }
+
+    fn accounts_page(
+        &self,
+        start: &primitives::types::Address,
+        limit: usize,
+    ) -> ProviderResult<AccountsPage> {
+        Ok(self
+            .db
+            .accounts_range_at_block(self.block_no, start, limit)?)
+    }
+
+    fn total_accounts(&self) -> ProviderResult<u64> {
+        Ok(self.db.account_count_at_block(self.block_no)?)
     }
 }
 
@@ -62,13 +115,352 @@ where
         self.state_by_block_number(block_no)
     }
     fn state_by_block_number(&self, block_no: u64) -> ProviderResult<traits::StateProviderBox> {
+        if block_no < self.db.earliest_state_block()? {
+            return Err(ProviderError::StatePruned(block_no));
+        }
         Ok(Box::new(PintStateProvider {
             db: self.db.clone(),
             block_no,
         }))
     }
 
-    fn state_by_block_hash(&self, header: BlockHash) -> ProviderResult<traits::StateProviderBox> {
-        todo!()
+    fn state_by_block_hash(&self, hash: BlockHash) -> ProviderResult<traits::StateProviderBox> {
+        let block_no = self
+            .db
+            .block_number_by_hash(&hash)?
+            .ok_or(ProviderError::BlockHashNotFound(hash))?;
+        self.state_by_block_number(block_no)
+    }
+}
+
+impl<DB> HeaderProvider for PintStateProviderFactory<DB>
+where
+    DB: Database,
+{
+    type Header = Header;
+
+    fn latest_header(&self) -> ProviderResult<Option<SealedHeader>> {
+        self.sealed_header(self.db.block_number())
+    }
+
+    fn sealed_header_by_hash(&self, block_hash: BlockHash) -> ProviderResult<Option<SealedHeader>> {
+        let Some(number) = self.db.block_number_by_hash(&block_hash)? else {
+            return Ok(None);
+        };
+        Ok(self
+            .db
+            .header(number)?
+            .map(|header| SealedHeader::new(header, block_hash)))
+    }
+
+    fn sealed_header(&self, number: u64) -> ProviderResult<Option<SealedHeader>> {
+        let (Some(header), Some(hash)) = (self.db.header(number)?, self.db.block_hash(number)?)
+        else {
+            return Ok(None);
+        };
+        Ok(Some(SealedHeader::new(header, hash)))
+    }
+
+    fn block_number(&self, hash: BlockHash) -> ProviderResult<Option<u64>> {
+        Ok(self.db.block_number_by_hash(&hash)?)
+    }
+}
+
+impl<DB> TransactionsProvider for PintStateProviderFactory<DB>
+where
+    DB: Database,
+{
+    type Transaction = TransactionSigned;
+
+    fn transaction_by_hash(&self, hash: TxHash) -> ProviderResult<Option<TransactionSigned>> {
+        let Some(meta) = self.db.transaction_meta(&hash)? else {
+            return Ok(None);
+        };
+        Ok(self
+            .db
+            .body(meta.block_number)?
+            .and_then(|body| body.transaction.into_iter().nth(meta.index as usize)))
+    }
+
+    fn transaction_block(&self, hash: TxHash) -> ProviderResult<Option<TransactionMeta>> {
+        Ok(self.db.transaction_meta(&hash)?)
+    }
+
+    fn receipt_by_hash(&self, hash: TxHash) -> ProviderResult<Option<Receipt>> {
+        self.receipt(hash)
+    }
+}
+
+impl<DB> ReceiptProvider for PintStateProviderFactory<DB>
+where
+    DB: Database,
+{
+    fn receipts_by_block(&self, number: u64) -> ProviderResult<Option<Vec<Receipt>>> {
+        Ok(self.db.receipts(number)?)
+    }
+
+    fn receipt(&self, tx_hash: TxHash) -> ProviderResult<Option<Receipt>> {
+        let Some(meta) = self.db.transaction_meta(&tx_hash)? else {
+            return Ok(None);
+        };
+        Ok(self
+            .db
+            .receipts(meta.block_number)?
+            .and_then(|receipts| receipts.into_iter().nth(meta.index as usize)))
+    }
+}
+
+impl<DB> BlockReader for PintStateProviderFactory<DB>
+where
+    DB: Database,
+{
+    type Block = Block<TransactionSigned>;
+
+    fn body_by_number(&self, number: u64) -> ProviderResult<Option<BlockBody<TransactionSigned>>> {
+        Ok(self.db.body(number)?)
+    }
+
+    fn block_by_number(&self, number: u64) -> ProviderResult<Option<SealedBlock<Self::Block>>> {
+        let (Some(header), Some(body)) = (self.sealed_header(number)?, self.db.body(number)?)
+        else {
+            return Ok(None);
+        };
+        Ok(Some(SealedBlock::new(header, body)))
+    }
+
+    fn block_by_hash(&self, hash: BlockHash) -> ProviderResult<Option<SealedBlock<Self::Block>>> {
+        match self.db.block_number_by_hash(&hash)? {
+            Some(number) => self.block_by_number(number),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use primitives::types::Address;
+    use transaction::traits::Decodable;
+
+    use super::*;
+    use crate::db::{DatabaseMut, ExecutedBlock, InMemoryDB};
+
+    pub(crate) fn make_transactions() -> Vec<TransactionSigned> {
+        [
+            "0000000000000000000000000000000000e0aa4e80c739ee08b5a6680586d1bf3991840c21000000000000000000000000000000010000000000000000000000000000000000000000000000000000000000000001be2855167f254060b5812e4a2849c7ba3d34ea4aeb175e87f83c2a7c1424379a6e722511c17cb5191e090b2a75dfe2b924d2b1bcbf0a2f26e207cb728dcaa34501",
+            "0000000000000000000000000000000000802d9a22dddb7b03ff11eea121bdd4a75135e4080000000000000000000000000000000100000000000000000000000000000000000000000000000000000000000000016969fda9b07fdf03f3092c06e9bd4def87edd1138b214be3ab724d980c0c12764b7150e282c63b1f42107b07a82a946d15ff56d921c2acd6fab423e22b94485f01",
+        ]
+        .iter()
+        .map(|raw| TransactionSigned::decode(&hex::decode(raw).unwrap()).unwrap().0)
+        .collect()
+    }
+
+    fn commit_empty_block(db: &InMemoryDB, parent: BlockHash, number: u64) -> BlockHash {
+        let header = Header {
+            previous_hash: parent,
+            number,
+            timestamp: number,
+            ..Default::default()
+        };
+        let block =
+            ExecutedBlock::new(header, BlockBody::default(), Vec::new(), Default::default());
+        let hash = block.hash;
+        db.commit_block(block).unwrap();
+        hash
+    }
+
+    #[test]
+    fn test_header_and_block_lookups() {
+        let db = InMemoryDB::new();
+        let provider = PintStateProviderFactory::new(db.clone());
+        assert!(provider.latest_header().unwrap().is_none());
+
+        let hash1 = commit_empty_block(&db, BlockHash::ZERO, 1);
+        let hash2 = commit_empty_block(&db, hash1, 2);
+
+        let latest = provider.latest_header().unwrap().unwrap();
+        assert_eq!(latest.hash(), hash2);
+        assert_eq!(latest.header().previous_hash, hash1);
+
+        let header1 = provider.sealed_header_by_hash(hash1).unwrap().unwrap();
+        assert_eq!(header1.number(), 1);
+        assert_eq!(header1.hash(), header1.header().hash_slow());
+        assert_eq!(provider.header_by_number(2).unwrap().unwrap().timestamp, 2);
+        assert_eq!(provider.block_number(hash2).unwrap(), Some(2));
+        assert_eq!(provider.block_number(BlockHash::random()).unwrap(), None);
+
+        let block = provider.block_by_hash(hash2).unwrap().unwrap();
+        assert_eq!(block.sealed_header().number(), 2);
+        assert!(block.body().transaction.is_empty());
+        assert!(provider.block_by_number(3).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_state_by_block_hash() {
+        let db = InMemoryDB::new();
+        let provider = PintStateProviderFactory::new(db.clone());
+        let hash1 = commit_empty_block(&db, BlockHash::ZERO, 1);
+
+        let state = provider.state_by_block_hash(hash1).unwrap();
+        assert!(state.account_balance(&Address::random()).unwrap().is_none());
+
+        let missing = BlockHash::random();
+        assert!(matches!(
+            provider.state_by_block_hash(missing),
+            Err(ProviderError::BlockHashNotFound(hash)) if hash == missing
+        ));
+    }
+
+    #[test]
+    fn test_historical_state_providers() {
+        let db = InMemoryDB::new();
+        let provider = PintStateProviderFactory::new(db.clone());
+        let address = Address::random();
+
+        let mut parent = BlockHash::ZERO;
+        for number in 1..=5u64 {
+            let account = Account {
+                nonce: number,
+                balance: primitives::types::U256::from(number * 100),
+            };
+            let header = Header {
+                previous_hash: parent,
+                number,
+                ..Default::default()
+            };
+            let diff = HashMap::from([(address.clone(), account)]);
+            let block = ExecutedBlock::new(header, BlockBody::default(), Vec::new(), diff);
+            parent = block.hash;
+            db.commit_block(block).unwrap();
+        }
+
+        assert!(
+            provider
+                .state_by_block_number(0)
+                .unwrap()
+                .account_nonce(&address)
+                .unwrap()
+                .is_none()
+        );
+        for number in 1..=5u64 {
+            let state = provider.state_by_block_number(number).unwrap();
+            assert_eq!(state.account_nonce(&address).unwrap(), Some(number));
+            assert_eq!(
+                state.account_balance(&address).unwrap(),
+                Some(primitives::types::U256::from(number * 100))
+            );
+        }
+        let latest = provider.latest().unwrap();
+        assert_eq!(latest.account_nonce(&address).unwrap(), Some(5));
+
+        let genesis = provider.state_by_block_number(0).unwrap();
+        assert_eq!(genesis.total_accounts().unwrap(), 0);
+        assert!(
+            genesis
+                .accounts_page(&Address::default(), 10)
+                .unwrap()
+                .accounts
+                .is_empty()
+        );
+        let page = provider
+            .state_by_block_number(2)
+            .unwrap()
+            .accounts_page(&Address::default(), 10)
+            .unwrap();
+        assert_eq!(page.accounts.len(), 1);
+        assert_eq!(page.accounts[0].1.nonce, 2);
+    }
+
+    #[test]
+    fn test_transaction_lookups() {
+        let db = InMemoryDB::new();
+        let provider = PintStateProviderFactory::new(db.clone());
+        commit_empty_block(&db, BlockHash::ZERO, 1);
+
+        let txs = make_transactions();
+        let hashes: Vec<_> = txs.iter().map(|tx| tx.hash()).collect();
+        let receipts: Vec<_> = hashes
+            .iter()
+            .map(|hash| Receipt {
+                tx_hash: *hash,
+                success: true,
+                ..Default::default()
+            })
+            .collect();
+        let header = Header {
+            previous_hash: db.block_hash(1).unwrap().unwrap(),
+            number: 2,
+            ..Default::default()
+        };
+        let block = ExecutedBlock::new(
+            header,
+            BlockBody { transaction: txs },
+            receipts.clone(),
+            Default::default(),
+        );
+        let block_hash = block.hash;
+        db.commit_block(block).unwrap();
+
+        let meta = provider.transaction_block(hashes[1]).unwrap().unwrap();
+        assert_eq!(
+            meta,
+            TransactionMeta {
+                block_hash,
+                block_number: 2,
+                index: 1
+            }
+        );
+        let tx = provider.transaction_by_hash(hashes[1]).unwrap().unwrap();
+        assert_eq!(tx.hash(), hashes[1]);
+        assert_eq!(
+            provider.receipt_by_hash(hashes[0]).unwrap(),
+            Some(receipts[0].clone())
+        );
+        assert_eq!(
+            provider.receipt_by_hash(hashes[1]).unwrap(),
+            Some(receipts[1].clone())
+        );
+
+        assert_eq!(provider.receipts_by_block(2).unwrap(), Some(receipts));
+        assert_eq!(provider.receipts_by_block(1).unwrap(), Some(Vec::new()));
+
+        let unknown = TxHash::random();
+        assert!(provider.transaction_block(unknown).unwrap().is_none());
+        assert!(provider.transaction_by_hash(unknown).unwrap().is_none());
+        assert!(provider.receipt_by_hash(unknown).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_unwind_returns_transactions() {
+        let db = InMemoryDB::new();
+        let provider = PintStateProviderFactory::new(db.clone());
+        let hash1 = commit_empty_block(&db, BlockHash::ZERO, 1);
+
+        let txs = make_transactions();
+        let hashes: Vec<_> = txs.iter().map(|tx| tx.hash()).collect();
+        let header = Header {
+            previous_hash: hash1,
+            number: 2,
+            ..Default::default()
+        };
+        let block = ExecutedBlock::new(
+            header,
+            BlockBody { transaction: txs },
+            vec![Receipt::default(), Receipt::default()],
+            Default::default(),
+        );
+        let hash2 = block.hash;
+        db.commit_block(block).unwrap();
+
+        let removed = db.unwind_to(1).unwrap();
+        let removed: Vec<_> = removed.iter().map(|tx| tx.hash()).collect();
+        assert_eq!(removed, hashes);
+
+        assert_eq!(provider.latest_header().unwrap().unwrap().hash(), hash1);
+        assert!(provider.sealed_header_by_hash(hash2).unwrap().is_none());
+        assert!(provider.block_by_number(2).unwrap().is_none());
+        assert!(provider.receipts_by_block(2).unwrap().is_none());
+        for hash in hashes {
+            assert!(provider.transaction_block(hash).unwrap().is_none());
+        }
     }
 }
@@ -0,0 +1,368 @@
+//! Schema versions of the on-disk database and the migrations between them.
+//! Every log starts with `magic | schema version`. Logs written before the version was recorded
+//! have no header and are version 1, which is only assumed if the log starts with a valid record.
+//!
+//! A migration upgrades the raw log by exactly one version, and [migrate] runs them in order
+//! until the log has the [SCHEMA_VERSION] of this node.
+use std::collections::BTreeMap;
+
+use primitives::types::BlockHash;
+use sha2::{Digest, Sha256};
+
+use crate::{
+    error::DatabaseError,
+    wal::{COMMIT, INIT, NextRecord, Record, decode_record, frame},
+};
+
+/// Schema version written by this node
+pub const SCHEMA_VERSION: u32 = 2;
+
+const MAGIC: &[u8; 8] = b"PINTWAL\0";
+
+/// Length of the header in front of the log records
+pub(crate) const HEADER_LEN: usize = MAGIC.len() + 4;
+
+// Upgrades a log of version `from` to version `from + 1`
+struct Migration {
+    from: u32,
+    migrate: fn(&[u8]) -> Result<Vec<u8>, DatabaseError>,
+}
+
+const MIGRATIONS: &[Migration] = &[Migration {
+    from: 1,
+    migrate: v1_to_v2,
+}];
+
+/// Header of a log with the current [SCHEMA_VERSION]
+pub(crate) fn header() -> [u8; HEADER_LEN] {
+    let mut header = [0; HEADER_LEN];
+    header[..MAGIC.len()].copy_from_slice(MAGIC);
+    header[MAGIC.len()..].copy_from_slice(&SCHEMA_VERSION.to_be_bytes());
+    header
+}
+
+/// Schema version of the given log.
+/// None for a new log, which is empty or holds a header that was torn while it was written.
+/// A log without a header must start with a complete version 1 record, anything else is not a
+/// log and fails with [DatabaseError::UnknownLogFormat].
+pub fn schema_version(log: &[u8]) -> Result<Option<u32>, DatabaseError> {
+    if log.len() < HEADER_LEN && header().starts_with(log) {
+        return Ok(None);
+    }
+    if let Some(rest) = log.strip_prefix(MAGIC) {
+        return Ok(Some(u32::from_be_bytes(
+            rest[..4].try_into().expect("4 bytes"),
+        )));
+    }
+    match next_v1_record(log, 0) {
+        NextRecord::Complete(payload, _) if decode_record(&v1_payload(payload)).is_some() => {
+            Ok(Some(1))
+        }
+        _ => Err(DatabaseError::UnknownLogFormat),
+    }
+}
+
+/// Upgrades the log to the [SCHEMA_VERSION] one version at a time.
+/// Returns None if the log is already up to date, so migrating a migrated log changes nothing.
+/// A new log gets the current header.
+pub fn migrate(log: &[u8]) -> Result<Option<Vec<u8>>, DatabaseError> {
+    let Some(mut version) = schema_version(log)? else {
+        return Ok(Some(header().to_vec()));
+    };
+    if version == SCHEMA_VERSION {
+        return Ok(None);
+    }
+    if version > SCHEMA_VERSION {
+        return Err(DatabaseError::SchemaVersionTooNew(version));
+    }
+
+    let mut log = log.to_vec();
+    while version < SCHEMA_VERSION {
+        let migration = MIGRATIONS
+            .iter()
+            .find(|migration| migration.from == version)
+            .ok_or(DatabaseError::UnsupportedSchemaVersion(version))?;
+        log = (migration.migrate)(&log)?;
+        version += 1;
+    }
+    Ok(Some(log))
+}
+
+// Version 2 records the schema version in front of the records, checks the length of every
+// record and its headers end with the beneficiary, which is the zero address for the blocks of
+// a version 1 log. The beneficiary is part of the block hash, so every parent hash is replaced
+// by the new hash of the parent.
+// A torn last record is dropped like opening the log would, a corrupted one before it fails.
+fn v1_to_v2(log: &[u8]) -> Result<Vec<u8>, DatabaseError> {
+    let mut migrated = Vec::with_capacity(HEADER_LEN + log.len());
+    migrated.extend_from_slice(MAGIC);
+    migrated.extend_from_slice(&2u32.to_be_bytes());
+    let mut hashes = BTreeMap::new();
+    let mut offset = 0;
+    while offset < log.len() {
+        match next_v1_record(log, offset) {
+            NextRecord::Complete(payload, end) => {
+                let payload = relink(v1_payload(payload), &mut hashes);
+                migrated.extend_from_slice(&frame(&payload));
+                offset = end;
+            }
+            NextRecord::Torn => break,
+            NextRecord::Corrupt => {
+                return Err(DatabaseError::CorruptLog {
+                    offset: offset as u64,
+                });
+            }
+        }
+    }
+    Ok(migrated)
+}
+
+// Record of a version 1 log, which is `payload length | payload | sha256 of the payload`.
+// The length is not checked, so a record running past the end of the log counts as torn.
+fn next_v1_record(log: &[u8], offset: usize) -> NextRecord<'_> {
+    let Some(len) = log.get(offset..offset + 4) else {
+        return NextRecord::Torn;
+    };
+    let start = offset + 4;
+    let end = start + u32::from_be_bytes(len.try_into().unwrap()) as usize;
+    if end + 32 > log.len() {
+        return NextRecord::Torn;
+    }
+    let payload = &log[start..end];
+    if Sha256::digest(payload).as_slice() == &log[end..end + 32] {
+        NextRecord::Complete(payload, end + 32)
+    } else if end + 32 == log.len() {
+        NextRecord::Torn
+    } else {
+        NextRecord::Corrupt
+    }
+}
+
+// kind + previous hash + number + roots + timestamp
+const BENEFICIARY_AT: usize = 1 + 32 + 8 + 3 * 32 + 8;
+
+// Payload of a version 1 record in the layout of version 2
+fn v1_payload(payload: &[u8]) -> Vec<u8> {
+    let mut payload = payload.to_vec();
+    if payload.len() >= BENEFICIARY_AT && matches!(payload[0], COMMIT | INIT) {
+        payload.splice(BENEFICIARY_AT..BENEFICIARY_AT, [0; 20]);
+    }
+    payload
+}
+
+// Points a migrated commit at the new hash of its parent and keeps the new hashes of the blocks
+// of the log, which `hashes` holds by number.
+fn relink(mut payload: Vec<u8>, hashes: &mut BTreeMap<u64, BlockHash>) -> Vec<u8> {
+    // the parent hash follows the kind, and an init record has the block hash after the header
+    const PARENT_AT: usize = 1;
+    const HASH_AT: usize = BENEFICIARY_AT + 20;
+
+    match decode_record(&payload) {
+        Some(Record::Commit(mut block)) => {
+            let parent = block.header.number.checked_sub(1);
+            if let Some(parent) = parent.and_then(|parent| hashes.get(&parent)) {
+                block.header.previous_hash = *parent;
+                payload[PARENT_AT..PARENT_AT + 32].copy_from_slice(parent.as_slice());
+            }
+            hashes.insert(block.header.number, block.header.hash_slow());
+        }
+        Some(Record::Init(header, ..)) => {
+            let hash = header.header().hash_slow();
+            payload[HASH_AT..HASH_AT + 32].copy_from_slice(hash.as_slice());
+            hashes.insert(header.number(), hash);
+        }
+        Some(Record::Unwind(number)) => {
+            hashes.split_off(&(number + 1));
+        }
+        _ => {}
+    }
+    payload
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf};
+
+    use primitives::{
+        account::Account,
+        types::{Address, B256, StorageKey, U256},
+    };
+
+    use super::*;
+    use crate::{
+        db::{Database, DatabaseMut},
+        tests::make_transactions,
+        wal::WalDB,
+    };
+
+    const V1_FIXTURE: &[u8] = include_bytes!("../fixtures/wal_v1.log");
+    const V2_FIXTURE: &[u8] = include_bytes!("../fixtures/wal_v2.log");
+
+    // Copies the fixture into a fresh file, because opening a database may rewrite it.
+    fn fixture_path(fixture: &[u8]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("pint-migration-{}", B256::random()));
+        fs::write(&path, fixture).unwrap();
+        path
+    }
+
+    // Both fixtures hold the same chain:
+    // blocks 1 to 3, where block 2 has two transactions, then an unwind to 2 and a new block 3.
+    fn assert_fixture_chain(db: &impl Database) {
+        assert_eq!(db.block_number(), 3);
+        let header = db.header(3).unwrap().unwrap();
+        assert_eq!(header.timestamp, 33);
+        assert_eq!(header.beneficiary, Address::default());
+        // the parents are linked by the hashes that cover the beneficiary
+        for number in 2..=3 {
+            let header = db.header(number).unwrap().unwrap();
+            assert_eq!(
+                Some(header.previous_hash),
+                db.block_hash(number - 1).unwrap()
+            );
+        }
+        assert_eq!(
+            db.basic(&Address::from_byte([1; 20])).unwrap(),
+            Some(Account {
+                nonce: 9,
+                balance: U256::from(999)
+            })
+        );
+        assert_eq!(
+            db.basic(&Address::from_byte([2; 20])).unwrap(),
+            Some(Account {
+                nonce: 2,
+                balance: U256::from(200)
+            })
+        );
+        assert_eq!(db.basic(&Address::from_byte([3; 20])).unwrap(), None);
+        assert_eq!(
+            db.storage(&Address::from_byte([2; 20]), &StorageKey::with_last_byte(2))
+                .unwrap(),
+            Some(U256::from(2))
+        );
+        let tx = &make_transactions()[1];
+        let meta = db.transaction_meta(&tx.hash()).unwrap().unwrap();
+        assert_eq!(meta.block_number, 2);
+        assert_eq!(db.receipts(2).unwrap().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_schema_version() {
+        assert_eq!(schema_version(&[]).unwrap(), None);
+        assert_eq!(schema_version(&header()[..5]).unwrap(), None);
+        assert_eq!(schema_version(&header()).unwrap(), Some(SCHEMA_VERSION));
+        assert_eq!(schema_version(V1_FIXTURE).unwrap(), Some(1));
+        assert_eq!(schema_version(V2_FIXTURE).unwrap(), Some(2));
+        // neither a header nor a version 1 record
+        for log in [&[0, 0][..], b"not a log file at all", &V1_FIXTURE[1..]] {
+            assert!(matches!(
+                schema_version(log),
+                Err(DatabaseError::UnknownLogFormat)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_migrate_is_idempotent() {
+        let migrated = migrate(V1_FIXTURE).unwrap().unwrap();
+        assert_eq!(schema_version(&migrated).unwrap(), Some(SCHEMA_VERSION));
+        assert_eq!(migrate(&migrated).unwrap(), None);
+        assert_eq!(migrated, V2_FIXTURE);
+    }
+
+    #[test]
+    fn test_open_migrates_v1_fixture() {
+        let path = fixture_path(V1_FIXTURE);
+        let db = WalDB::open(&path).unwrap();
+        assert_fixture_chain(&db);
+        let migrated = fs::read(&path).unwrap();
+        assert_eq!(schema_version(&migrated).unwrap(), Some(SCHEMA_VERSION));
+        // the temporary file is gone after the rename
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".migrate");
+        assert!(!PathBuf::from(tmp).exists());
+
+        // the migrated log keeps working and reopening does not migrate again
+        let number = db.block_number() + 1;
+        let header = primitives::block::header::Header {
+            previous_hash: db.block_hash(number - 1).unwrap().unwrap(),
+            number,
+            ..Default::default()
+        };
+        db.commit_block(crate::db::ExecutedBlock::new(
+            header,
+            Default::default(),
+            Vec::new(),
+            Default::default(),
+        ))
+        .unwrap();
+        drop(db);
+        let before = fs::read(&path).unwrap();
+        let reopened = WalDB::open(&path).unwrap();
+        assert_eq!(reopened.block_number(), 4);
+        assert_eq!(fs::read(&path).unwrap(), before);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_open_v2_fixture() {
+        let path = fixture_path(V2_FIXTURE);
+        assert_fixture_chain(&WalDB::open(&path).unwrap());
+        // the log is up to date and left as it is
+        assert_eq!(fs::read(&path).unwrap(), V2_FIXTURE);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_v1_to_v2_drops_the_torn_tail() {
+        let mut tail = (200u32.to_be_bytes()).to_vec();
+        tail.extend_from_slice(&[COMMIT; 200]);
+        tail.extend_from_slice(&Sha256::digest([COMMIT; 200]));
+        let mut log = V1_FIXTURE.to_vec();
+        log.extend_from_slice(&tail[..tail.len() - 1]);
+        assert_eq!(v1_to_v2(&log).unwrap(), V2_FIXTURE);
+
+        // a corrupted record before the tail is not dropped with it
+        let mut log = V1_FIXTURE.to_vec();
+        log[10] ^= 1;
+        assert!(matches!(
+            v1_to_v2(&log),
+            Err(DatabaseError::CorruptLog { offset: 0 })
+        ));
+    }
+
+    #[test]
+    fn test_open_read_only_migrates_in_memory() {
+        let path = fixture_path(V1_FIXTURE);
+        let db = WalDB::open_read_only(&path).unwrap();
+        assert_fixture_chain(&db);
+        assert_eq!(fs::read(&path).unwrap(), V1_FIXTURE);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_newer_schema_is_refused() {
+        let mut log = V2_FIXTURE.to_vec();
+        log[MAGIC.len()..HEADER_LEN].copy_from_slice(&(SCHEMA_VERSION + 1).to_be_bytes());
+        let path = fixture_path(&log);
+
+        assert!(matches!(
+            WalDB::open(&path),
+            Err(DatabaseError::SchemaVersionTooNew(version)) if version == SCHEMA_VERSION + 1
+        ));
+        // the log is left untouched for the newer node
+        assert_eq!(fs::read(&path).unwrap(), log);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_unknown_file_is_refused() {
+        let path = fixture_path(b"some other file");
+        assert!(matches!(
+            WalDB::open(&path),
+            Err(DatabaseError::UnknownLogFormat)
+        ));
+        assert_eq!(fs::read(&path).unwrap(), b"some other file");
+        fs::remove_file(path).unwrap();
+    }
+}
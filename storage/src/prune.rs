@@ -0,0 +1,547 @@
+//! Pruning of historical data.
+//! The [Pruner] runs on its own thread and a [PruningDB] wakes it up after every block commit.
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    sync::{
+        Arc, Mutex,
+        mpsc::{self, Sender},
+    },
+    thread::{self, JoinHandle},
+};
+
+use primitives::{
+    account::{Account, AccountStorage},
+    block::{
+        body::BlockBody,
+        header::{Header, SealedHeader},
+    },
+    receipt::Receipt,
+    types::{Address, BlockHash, StorageKey, StorageValue, TxHash},
+};
+use tracing::warn;
+use transaction::TransactionSigned;
+
+use crate::{
+    db::{
+        AccountChangeset, AccountsPage, Database, DatabaseMut, ExecutedBlock, StorageChangeset,
+        TransactionMeta,
+    },
+    error::DatabaseError,
+};
+
+/// Blocks of history that [PruneMode::Minimal] keeps, so short reorgs can still be unwound
+pub const MINIMAL_PRUNE_DISTANCE: u64 = 64;
+
+/// How much history the node keeps
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PruneMode {
+    /// Keep everything
+    #[default]
+    Archive,
+    /// Keep the state history of the last `n` blocks, but never less than
+    /// [MINIMAL_PRUNE_DISTANCE]. Blocks and receipts are kept.
+    Full(u64),
+    /// Keep only the state history needed for unwinding.
+    /// Receipts and the transaction index of older blocks are dropped too, so their
+    /// transactions cannot be looked up by hash anymore although the block bodies are kept.
+    Minimal,
+}
+
+/// Parses `archive`, `full:<blocks>` or `minimal`
+impl FromStr for PruneMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            None if s == "archive" => Ok(PruneMode::Archive),
+            None if s == "minimal" => Ok(PruneMode::Minimal),
+            Some(("full", distance)) => distance
+                .parse()
+                .map(PruneMode::Full)
+                .map_err(|e| format!("invalid distance {:?}: {}", distance, e)),
+            _ => Err(format!(
+                "unknown prune mode {:?}, expected archive, full:<blocks> or minimal",
+                s
+            )),
+        }
+    }
+}
+
+impl PruneMode {
+    // Every state history below the returned block number can be pruned.
+    fn state_target(&self, tip: u64) -> Option<u64> {
+        match self {
+            PruneMode::Archive => None,
+            PruneMode::Full(distance) => {
+                Some(tip.saturating_sub((*distance).max(MINIMAL_PRUNE_DISTANCE)))
+            }
+            PruneMode::Minimal => Some(tip.saturating_sub(MINIMAL_PRUNE_DISTANCE)),
+        }
+    }
+
+    // Every receipt below the returned block number can be pruned.
+    fn receipts_target(&self, tip: u64) -> Option<u64> {
+        match self {
+            PruneMode::Minimal => self.state_target(tip),
+            _ => None,
+        }
+    }
+}
+
+/// Result of a single pruner run
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct PruneOutput {
+    pub state_blocks: u64,
+    pub receipt_blocks: u64,
+}
+
+/// Removes history that is not needed anymore according to the [PruneMode]
+pub struct Pruner<DB> {
+    db: DB,
+    mode: PruneMode,
+}
+
+impl<DB> Pruner<DB>
+where
+    DB: DatabaseMut,
+{
+    pub fn new(db: DB, mode: PruneMode) -> Self {
+        Self { db, mode }
+    }
+
+    /// Prunes the history relative to the given tip
+    pub fn run(&self, tip: u64) -> Result<PruneOutput, DatabaseError> {
+        let mut output = PruneOutput::default();
+        if let Some(target) = self.mode.state_target(tip) {
+            output.state_blocks = self.db.prune_state_history(target)?;
+        }
+        if let Some(target) = self.mode.receipts_target(tip) {
+            output.receipt_blocks = self.db.prune_receipts(target)?;
+        }
+        Ok(output)
+    }
+}
+
+impl<DB> Pruner<DB>
+where
+    DB: DatabaseMut + Send + 'static,
+{
+    /// Spawns the pruner on a background thread
+    pub fn spawn(self) -> PrunerHandle {
+        let (to_pruner, from_handle) = mpsc::channel::<u64>();
+        let error = Arc::new(Mutex::new(None));
+        let last_error = error.clone();
+        let thread = thread::spawn(move || {
+            while let Ok(mut tip) = from_handle.recv() {
+                // Only the newest tip matters if several commits happened meanwhile.
+                while let Ok(newer) = from_handle.try_recv() {
+                    tip = newer;
+                }
+                // A failed run is retried with the next commit.
+                if let Err(err) = self.run(tip) {
+                    warn!(target: "pruner", tip, %err, "Pruning failed");
+                    if let Ok(mut last) = last_error.lock() {
+                        *last = Some(err);
+                    }
+                }
+            }
+        });
+
+        PrunerHandle {
+            to_pruner,
+            error,
+            thread: Some(thread),
+        }
+    }
+}
+
+/// Channel handle to the background [Pruner].
+/// Dropping it stops the pruner after the pending runs.
+pub struct PrunerHandle {
+    to_pruner: Sender<u64>,
+    // latest failed run that nobody took yet
+    error: Arc<Mutex<Option<DatabaseError>>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl PrunerHandle {
+    /// Notifies the pruner that a block was committed
+    pub fn on_commit(&self, tip: u64) {
+        let _ = self.to_pruner.send(tip);
+    }
+
+    /// Takes the error of the latest failed run
+    pub fn take_error(&self) -> Option<DatabaseError> {
+        self.error.lock().ok()?.take()
+    }
+
+    /// Stops the pruner and waits until it finished
+    pub fn shutdown(mut self) {
+        self.stop();
+    }
+
+    fn stop(&mut self) {
+        // Closing the channel ends the pruner loop.
+        let (closed, _) = mpsc::channel();
+        self.to_pruner = closed;
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for PrunerHandle {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Database that wakes up a background [Pruner] after every commit, whoever commits
+pub struct PruningDB<DB> {
+    db: DB,
+    pruner: Arc<PrunerHandle>,
+}
+
+impl<DB> Clone for PruningDB<DB>
+where
+    DB: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            db: self.db.clone(),
+            pruner: self.pruner.clone(),
+        }
+    }
+}
+
+impl<DB> PruningDB<DB>
+where
+    DB: DatabaseMut + Clone + Send + 'static,
+{
+    /// Spawns a pruner of the given mode for the database
+    pub fn new(db: DB, mode: PruneMode) -> Self {
+        let pruner = Pruner::new(db.clone(), mode).spawn();
+        Self {
+            db,
+            pruner: Arc::new(pruner),
+        }
+    }
+}
+
+impl<DB> PruningDB<DB> {
+    /// Database the pruner runs on
+    pub fn db(&self) -> &DB {
+        &self.db
+    }
+
+    /// Handle of the background pruner
+    pub fn pruner(&self) -> &PrunerHandle {
+        &self.pruner
+    }
+}
+
+impl<DB: Database> Database for PruningDB<DB> {
+    fn basic(&self, address: &Address) -> Result<Option<Account>, DatabaseError> {
+        self.db.basic(address)
+    }
+
+    fn basic_at_block(
+        &self,
+        address: &Address,
+        number: u64,
+    ) -> Result<Option<Account>, DatabaseError> {
+        self.db.basic_at_block(address, number)
+    }
+
+    fn block_hash(&self, number: u64) -> Result<Option<BlockHash>, DatabaseError> {
+        self.db.block_hash(number)
+    }
+
+    fn block_number(&self) -> u64 {
+        self.db.block_number()
+    }
+
+    fn copy_state_from_block_no(
+        &self,
+        number: u64,
+    ) -> Result<HashMap<Address, Account>, DatabaseError> {
+        self.db.copy_state_from_block_no(number)
+    }
+
+    fn header(&self, number: u64) -> Result<Option<Header>, DatabaseError> {
+        self.db.header(number)
+    }
+
+    fn body(&self, number: u64) -> Result<Option<BlockBody<TransactionSigned>>, DatabaseError> {
+        self.db.body(number)
+    }
+
+    fn block_number_by_hash(&self, hash: &BlockHash) -> Result<Option<u64>, DatabaseError> {
+        self.db.block_number_by_hash(hash)
+    }
+
+    fn transaction_meta(&self, hash: &TxHash) -> Result<Option<TransactionMeta>, DatabaseError> {
+        self.db.transaction_meta(hash)
+    }
+
+    fn receipts(&self, number: u64) -> Result<Option<Vec<Receipt>>, DatabaseError> {
+        self.db.receipts(number)
+    }
+
+    fn earliest_state_block(&self) -> Result<u64, DatabaseError> {
+        self.db.earliest_state_block()
+    }
+
+    fn accounts_range(&self, start: &Address, limit: usize) -> Result<AccountsPage, DatabaseError> {
+        self.db.accounts_range(start, limit)
+    }
+
+    fn account_count(&self) -> Result<u64, DatabaseError> {
+        self.db.account_count()
+    }
+
+    fn accounts_range_at_block(
+        &self,
+        number: u64,
+        start: &Address,
+        limit: usize,
+    ) -> Result<AccountsPage, DatabaseError> {
+        self.db.accounts_range_at_block(number, start, limit)
+    }
+
+    fn account_count_at_block(&self, number: u64) -> Result<u64, DatabaseError> {
+        self.db.account_count_at_block(number)
+    }
+
+    fn storage(
+        &self,
+        address: &Address,
+        key: &StorageKey,
+    ) -> Result<Option<StorageValue>, DatabaseError> {
+        self.db.storage(address, key)
+    }
+
+    fn storage_at_block(
+        &self,
+        address: &Address,
+        key: &StorageKey,
+        number: u64,
+    ) -> Result<Option<StorageValue>, DatabaseError> {
+        self.db.storage_at_block(address, key, number)
+    }
+
+    fn copy_storage_from_block_no(
+        &self,
+        number: u64,
+    ) -> Result<HashMap<Address, AccountStorage>, DatabaseError> {
+        self.db.copy_storage_from_block_no(number)
+    }
+
+    fn account_changeset(&self, number: u64) -> Result<Option<AccountChangeset>, DatabaseError> {
+        self.db.account_changeset(number)
+    }
+
+    fn storage_changeset(&self, number: u64) -> Result<Option<StorageChangeset>, DatabaseError> {
+        self.db.storage_changeset(number)
+    }
+}
+
+impl<DB: DatabaseMut> DatabaseMut for PruningDB<DB> {
+    fn commit_block(&self, block: ExecutedBlock) -> Result<(), DatabaseError> {
+        self.db.commit_block(block)?;
+        self.pruner.on_commit(self.db.block_number());
+        Ok(())
+    }
+
+    fn unwind_to(&self, number: u64) -> Result<Vec<TransactionSigned>, DatabaseError> {
+        self.db.unwind_to(number)
+    }
+
+    fn prune_state_history(&self, number: u64) -> Result<u64, DatabaseError> {
+        self.db.prune_state_history(number)
+    }
+
+    fn prune_receipts(&self, number: u64) -> Result<u64, DatabaseError> {
+        self.db.prune_receipts(number)
+    }
+
+    fn init_from_state(
+        &self,
+        header: SealedHeader,
+        state: HashMap<Address, Account>,
+        storage: HashMap<Address, AccountStorage>,
+    ) -> Result<(), DatabaseError> {
+        self.db.init_from_state(header, state, storage)?;
+        self.pruner.on_commit(self.db.block_number());
+        Ok(())
+    }
+
+    fn rebuild_indices(&self) -> Result<u64, DatabaseError> {
+        self.db.rebuild_indices()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use primitives::{
+        block::{body::BlockBody, header::Header},
+        types::BlockHash,
+    };
+
+    use super::*;
+    use crate::{
+        PintStateProviderFactory,
+        db::{Database, ExecutedBlock, InMemoryDB},
+        error::ProviderError,
+        traits::StateProviderFactory,
+    };
+
+    fn commit_empty_blocks(db: &impl DatabaseMut, count: u64) {
+        for _ in 0..count {
+            let number = db.block_number() + 1;
+            let header = Header {
+                previous_hash: db
+                    .block_hash(number - 1)
+                    .unwrap()
+                    .unwrap_or(BlockHash::ZERO),
+                number,
+                ..Default::default()
+            };
+            let block =
+                ExecutedBlock::new(header, BlockBody::default(), Vec::new(), Default::default());
+            db.commit_block(block).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_full_mode_keeps_last_blocks() {
+        let db = InMemoryDB::new();
+        let provider = PintStateProviderFactory::new(db.clone());
+        commit_empty_blocks(&db, MINIMAL_PRUNE_DISTANCE + 10);
+
+        let pruner = Pruner::new(db.clone(), PruneMode::Full(MINIMAL_PRUNE_DISTANCE + 3));
+        let output = pruner.run(db.block_number()).unwrap();
+        assert_eq!(
+            output,
+            PruneOutput {
+                state_blocks: 7,
+                receipt_blocks: 0
+            }
+        );
+        assert_eq!(
+            pruner.run(db.block_number()).unwrap(),
+            PruneOutput::default()
+        );
+
+        assert!(matches!(
+            provider.state_by_block_number(6),
+            Err(ProviderError::StatePruned(6))
+        ));
+        assert!(provider.state_by_block_number(7).is_ok());
+        assert!(db.copy_state_from_block_no(7).is_ok());
+        // blocks themselves are kept
+        assert!(db.header(1).unwrap().is_some());
+        assert!(db.receipts(1).unwrap().is_some());
+
+        // history below the pruned height cannot be unwound
+        assert!(db.unwind_to(5).is_err());
+        assert!(db.unwind_to(7).is_ok());
+    }
+
+    #[test]
+    fn test_full_mode_keeps_at_least_the_minimal_window() {
+        let tip = MINIMAL_PRUNE_DISTANCE + 10;
+        for distance in [0, 1, MINIMAL_PRUNE_DISTANCE] {
+            assert_eq!(PruneMode::Full(distance).state_target(tip), Some(10));
+        }
+        assert_eq!(PruneMode::Full(tip).state_target(tip), Some(0));
+
+        let db = InMemoryDB::new();
+        commit_empty_blocks(&db, 10);
+        let output = Pruner::new(db.clone(), PruneMode::Full(1)).run(10).unwrap();
+        assert_eq!(output, PruneOutput::default());
+        assert!(db.unwind_to(0).is_ok());
+    }
+
+    #[test]
+    fn test_minimal_mode_prunes_receipts() {
+        let db = InMemoryDB::new();
+        commit_empty_blocks(&db, MINIMAL_PRUNE_DISTANCE + 2);
+
+        let output = Pruner::new(db.clone(), PruneMode::Minimal)
+            .run(db.block_number())
+            .unwrap();
+        assert_eq!(
+            output,
+            PruneOutput {
+                state_blocks: 2,
+                receipt_blocks: 2
+            }
+        );
+        assert!(db.receipts(1).unwrap().is_none());
+        assert!(db.receipts(2).unwrap().is_some());
+        assert_eq!(db.earliest_state_block().unwrap(), 2);
+    }
+
+    #[test]
+    fn test_archive_mode_prunes_nothing() {
+        let db = InMemoryDB::new();
+        commit_empty_blocks(&db, 5);
+
+        let output = Pruner::new(db.clone(), PruneMode::Archive).run(5).unwrap();
+        assert_eq!(output, PruneOutput::default());
+        assert!(db.copy_state_from_block_no(0).is_ok());
+    }
+
+    #[test]
+    fn test_background_pruner() {
+        let db = InMemoryDB::new();
+        let handle = Pruner::new(db.clone(), PruneMode::Full(1)).spawn();
+        commit_empty_blocks(&db, MINIMAL_PRUNE_DISTANCE + 3);
+        handle.on_commit(db.block_number());
+        assert!(handle.take_error().is_none());
+        handle.shutdown();
+
+        assert_eq!(db.earliest_state_block().unwrap(), 3);
+    }
+
+    #[test]
+    fn test_pruning_db_wakes_the_pruner_on_commit() {
+        let db = InMemoryDB::new();
+        let pruning = PruningDB::new(db.clone(), PruneMode::Minimal);
+        commit_empty_blocks(&pruning, MINIMAL_PRUNE_DISTANCE + 3);
+        // dropping the last handle waits for the pending runs
+        drop(pruning);
+
+        assert_eq!(db.earliest_state_block().unwrap(), 3);
+        assert!(db.receipts(2).unwrap().is_none());
+        assert!(db.receipts(3).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_prune_mode_from_str() {
+        assert_eq!("archive".parse(), Ok(PruneMode::Archive));
+        assert_eq!("full:128".parse(), Ok(PruneMode::Full(128)));
+        assert_eq!("minimal".parse(), Ok(PruneMode::Minimal));
+        for invalid in ["", "full", "full:", "full:-1", "minimal:1", "everything"] {
+            assert!(invalid.parse::<PruneMode>().is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_background_pruner_keeps_the_error() {
+        let db = InMemoryDB::new();
+        commit_empty_blocks(&db, 1);
+        db.poison();
+
+        let mut handle = Pruner::new(db.clone(), PruneMode::Minimal).spawn();
+        handle.on_commit(1);
+        handle.stop();
+        assert!(matches!(
+            handle.take_error(),
+            Some(DatabaseError::LockError)
+        ));
+        assert!(handle.take_error().is_none());
+    }
+}
@@ -0,0 +1,396 @@
+//! State snapshots.
+//! A snapshot is the header of a block followed by the state at that block,
+//! as a stream of checksummed chunks of accounts sorted by address.
+//!
+//! Layout:
+//! `magic | version | header | block hash | account count | chunk count | chunks..`
+//! and every chunk is `account count | accounts.. | sha256 of the chunk`,
+//! where an account is `address | nonce | balance | slot count | (key, value)..`
+use std::{
+    collections::HashMap,
+    io::{Read, Write},
+};
+
+use primitives::{
+    account::{Account, AccountStorage},
+    block::header::{Header, SealedHeader},
+    proofs::calculate_state_root,
+    types::{Address, B256, StorageKey, StorageValue, U256},
+};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    db::{Database, DatabaseMut},
+    error::SnapshotError,
+};
+
+const MAGIC: &[u8; 8] = b"PINTSNAP";
+// 2 added the account storage
+const VERSION: u8 = 2;
+// address + nonce + balance
+const ACCOUNT_LEN: usize = 20 + 8 + 32;
+// key + value
+const SLOT_LEN: usize = 32 + 32;
+/// Accounts per chunk
+pub const DEFAULT_CHUNK_SIZE: usize = 1024;
+
+/// First part of a snapshot
+#[derive(Debug, Clone)]
+pub struct SnapshotHeader {
+    pub header: SealedHeader,
+    pub account_count: u64,
+    pub chunk_count: u64,
+}
+
+/// State of a snapshot
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SnapshotState {
+    pub accounts: HashMap<Address, Account>,
+    pub storage: HashMap<Address, AccountStorage>,
+}
+
+/// Writes the state of a block of any [Database] as a snapshot
+pub struct SnapshotExporter<DB> {
+    db: DB,
+    chunk_size: usize,
+}
+
+impl<DB: Database> SnapshotExporter<DB> {
+    pub fn new(db: DB) -> Self {
+        Self {
+            db,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+        }
+    }
+
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
+
+    /// Exports the state at the given block number
+    pub fn export<W: Write>(
+        &self,
+        number: u64,
+        writer: &mut W,
+    ) -> Result<SnapshotHeader, SnapshotError> {
+        let (Some(header), Some(hash)) = (self.db.header(number)?, self.db.block_hash(number)?)
+        else {
+            return Err(SnapshotError::HeaderNotFound(number));
+        };
+        let state = self.db.copy_state_from_block_no(number)?;
+        let storage = self.db.copy_storage_from_block_no(number)?;
+
+        // Never write a snapshot that cannot be imported.
+        let state_root = calculate_state_root(&state, &storage);
+        if state_root != header.state_root {
+            return Err(SnapshotError::StateRootMismatch {
+                expected: header.state_root,
+                got: state_root,
+            });
+        }
+
+        let mut accounts: Vec<_> = state.into_iter().collect();
+        accounts.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+
+        let snapshot = SnapshotHeader {
+            header: SealedHeader::new(header, hash),
+            account_count: accounts.len() as u64,
+            chunk_count: accounts.len().div_ceil(self.chunk_size) as u64,
+        };
+        write_header(writer, &snapshot)?;
+
+        let empty = AccountStorage::new();
+        for chunk in accounts.chunks(self.chunk_size) {
+            let mut payload = Vec::with_capacity(4 + chunk.len() * (ACCOUNT_LEN + 4));
+            payload.extend_from_slice(&(chunk.len() as u32).to_be_bytes());
+            for (address, account) in chunk {
+                let slots = storage.get(address).unwrap_or(&empty);
+                payload.extend_from_slice(address.get_addr());
+                payload.extend_from_slice(&account.nonce.to_be_bytes());
+                payload.extend_from_slice(&account.balance.to_be_bytes::<32>());
+                payload.extend_from_slice(&(slots.len() as u32).to_be_bytes());
+                for (key, value) in slots {
+                    payload.extend_from_slice(key.as_slice());
+                    payload.extend_from_slice(&value.to_be_bytes::<32>());
+                }
+            }
+            writer.write_all(&payload)?;
+            writer.write_all(&Sha256::digest(&payload))?;
+        }
+        writer.flush()?;
+
+        Ok(snapshot)
+    }
+}
+
+/// Verifies a snapshot and seeds a fresh database with it
+pub struct SnapshotImporter<DB> {
+    db: DB,
+}
+
+impl<DB: DatabaseMut> SnapshotImporter<DB> {
+    pub fn new(db: DB) -> Self {
+        Self { db }
+    }
+
+    /// Imports the snapshot. Nothing is written unless every chunk and the state root are valid.
+    pub fn import<R: Read>(&self, reader: &mut R) -> Result<SnapshotHeader, SnapshotError> {
+        let (snapshot, state) = read_snapshot(reader)?;
+        self.db
+            .init_from_state(snapshot.header.clone(), state.accounts, state.storage)?;
+        Ok(snapshot)
+    }
+}
+
+/// Reads and verifies a whole snapshot
+pub fn read_snapshot<R: Read>(
+    reader: &mut R,
+) -> Result<(SnapshotHeader, SnapshotState), SnapshotError> {
+    let snapshot = read_header(reader)?;
+
+    let mut state = SnapshotState {
+        accounts: HashMap::with_capacity(snapshot.account_count as usize),
+        storage: HashMap::new(),
+    };
+    let mut last: Option<Address> = None;
+    for index in 0..snapshot.chunk_count {
+        // The chunk is only trusted after its checksum matched.
+        let mut hasher = Sha256::new();
+        let len: [u8; 4] = read_array(reader)?;
+        hasher.update(len);
+        let mut accounts = Vec::with_capacity(u32::from_be_bytes(len) as usize);
+        for _ in 0..u32::from_be_bytes(len) {
+            let raw: [u8; ACCOUNT_LEN] = read_array(reader)?;
+            let slot_count: [u8; 4] = read_array(reader)?;
+            hasher.update(raw);
+            hasher.update(slot_count);
+
+            let mut slots = AccountStorage::new();
+            for _ in 0..u32::from_be_bytes(slot_count) {
+                let slot: [u8; SLOT_LEN] = read_array(reader)?;
+                hasher.update(slot);
+                slots.insert(
+                    StorageKey::from_slice(&slot[..32]),
+                    StorageValue::from_be_slice(&slot[32..]),
+                );
+            }
+            accounts.push((raw, slots));
+        }
+        let checksum: [u8; 32] = read_array(reader)?;
+        if hasher.finalize().as_slice() != checksum {
+            return Err(SnapshotError::ChecksumMismatch(index));
+        }
+
+        for (raw, slots) in accounts {
+            let address = Address::from_byte(raw[..20].try_into().expect("20 bytes"));
+            if last.as_ref().is_some_and(|last| *last >= address) {
+                return Err(SnapshotError::UnsortedAccounts);
+            }
+            let account = Account {
+                nonce: u64::from_be_bytes(raw[20..28].try_into().expect("8 bytes")),
+                balance: U256::from_be_slice(&raw[28..]),
+            };
+            last = Some(address.clone());
+            if !slots.is_empty() {
+                state.storage.insert(address.clone(), slots);
+            }
+            state.accounts.insert(address, account);
+        }
+    }
+
+    if state.accounts.len() as u64 != snapshot.account_count {
+        return Err(SnapshotError::AccountCountMismatch(
+            state.accounts.len() as u64
+        ));
+    }
+    let state_root = calculate_state_root(&state.accounts, &state.storage);
+    let expected = snapshot.header.header().state_root;
+    if state_root != expected {
+        return Err(SnapshotError::StateRootMismatch {
+            expected,
+            got: state_root,
+        });
+    }
+
+    Ok((snapshot, state))
+}
+
+fn write_header<W: Write>(writer: &mut W, snapshot: &SnapshotHeader) -> std::io::Result<()> {
+    let header = snapshot.header.header();
+    writer.write_all(MAGIC)?;
+    writer.write_all(&[VERSION])?;
+    writer.write_all(header.previous_hash.as_slice())?;
+    writer.write_all(&header.number.to_be_bytes())?;
+    writer.write_all(header.transaction_root.as_slice())?;
+    writer.write_all(header.state_root.as_slice())?;
+    writer.write_all(header.receipts_root.as_slice())?;
+    writer.write_all(&header.timestamp.to_be_bytes())?;
+    writer.write_all(header.beneficiary.get_addr())?;
+    writer.write_all(snapshot.header.hash().as_slice())?;
+    writer.write_all(&snapshot.account_count.to_be_bytes())?;
+    writer.write_all(&snapshot.chunk_count.to_be_bytes())?;
+    Ok(())
+}
+
+fn read_header<R: Read>(reader: &mut R) -> Result<SnapshotHeader, SnapshotError> {
+    let magic: [u8; 8] = read_array(reader)?;
+    if &magic != MAGIC {
+        return Err(SnapshotError::InvalidMagic);
+    }
+    let [version] = read_array(reader)?;
+    if version != VERSION {
+        return Err(SnapshotError::UnsupportedVersion(version));
+    }
+
+    let header = Header {
+        previous_hash: B256::from(read_array::<_, 32>(reader)?),
+        number: u64::from_be_bytes(read_array(reader)?),
+        transaction_root: B256::from(read_array::<_, 32>(reader)?),
+        state_root: B256::from(read_array::<_, 32>(reader)?),
+        receipts_root: B256::from(read_array::<_, 32>(reader)?),
+        timestamp: u64::from_be_bytes(read_array(reader)?),
+        beneficiary: Address::from_byte(read_array(reader)?),
+    };
+    let hash = B256::from(read_array::<_, 32>(reader)?);
+    if header.hash_slow() != hash {
+        return Err(SnapshotError::BlockHashMismatch(hash));
+    }
+
+    Ok(SnapshotHeader {
+        header: SealedHeader::new(header, hash),
+        account_count: u64::from_be_bytes(read_array(reader)?),
+        chunk_count: u64::from_be_bytes(read_array(reader)?),
+    })
+}
+
+fn read_array<R: Read, const N: usize>(reader: &mut R) -> std::io::Result<[u8; N]> {
+    let mut buf = [0u8; N];
+    reader.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use primitives::{block::body::BlockBody, types::BlockHash};
+
+    use super::*;
+    use crate::db::{ExecutedBlock, InMemoryDB};
+
+    // Every account has a single storage slot.
+    fn make_db(accounts: usize) -> (InMemoryDB, SnapshotState) {
+        let db = InMemoryDB::new();
+        let mut state = SnapshotState::default();
+        for i in 0..accounts {
+            let address = Address::random();
+            let account = Account {
+                nonce: i as u64,
+                balance: U256::from(i * 10),
+            };
+            let slots = AccountStorage::from([(StorageKey::random(), StorageValue::from(i + 1))]);
+            state.accounts.insert(address.clone(), account);
+            state.storage.insert(address, slots);
+        }
+        let header = Header {
+            previous_hash: BlockHash::ZERO,
+            number: 1,
+            state_root: calculate_state_root(&state.accounts, &state.storage),
+            beneficiary: Address::from_byte([0xbe; 20]),
+            ..Default::default()
+        };
+        let block = ExecutedBlock::new(
+            header,
+            BlockBody::default(),
+            Vec::new(),
+            state.accounts.clone(),
+        )
+        .with_storage_diff(state.storage.clone());
+        db.commit_block(block).unwrap();
+        (db, state)
+    }
+
+    #[test]
+    fn test_export_and_import() {
+        let (db, state) = make_db(10);
+        let mut buf = Vec::new();
+        let exported = SnapshotExporter::new(db.clone())
+            .with_chunk_size(3)
+            .export(1, &mut buf)
+            .unwrap();
+        assert_eq!(exported.account_count, 10);
+        assert_eq!(exported.chunk_count, 4);
+
+        let fresh = InMemoryDB::new();
+        let imported = SnapshotImporter::new(fresh.clone())
+            .import(&mut buf.as_slice())
+            .unwrap();
+        assert_eq!(imported.header.hash(), db.block_hash(1).unwrap().unwrap());
+        assert_eq!(imported.header.header(), &db.header(1).unwrap().unwrap());
+        assert_eq!(fresh.block_number(), 1);
+        assert_eq!(fresh.block_hash(1).unwrap(), db.block_hash(1).unwrap());
+        assert_eq!(fresh.copy_state_from_block_no(1).unwrap(), state.accounts);
+        assert_eq!(fresh.copy_storage_from_block_no(1).unwrap(), state.storage);
+        assert_eq!(fresh.earliest_state_block().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_import_rejects_corrupted_chunk() {
+        let (db, _) = make_db(5);
+        let mut buf = Vec::new();
+        SnapshotExporter::new(db).export(1, &mut buf).unwrap();
+
+        // flip a bit inside the storage of the last account
+        let at = buf.len() - 33;
+        buf[at] ^= 1;
+        let fresh = InMemoryDB::new();
+        assert!(matches!(
+            SnapshotImporter::new(fresh.clone()).import(&mut buf.as_slice()),
+            Err(SnapshotError::ChecksumMismatch(0))
+        ));
+        assert!(fresh.header(1).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_import_rejects_wrong_state_root() {
+        let (db, _) = make_db(4);
+        let mut buf = Vec::new();
+        SnapshotExporter::new(db)
+            .with_chunk_size(2)
+            .export(1, &mut buf)
+            .unwrap();
+
+        // drop the last chunk and pretend there was only one
+        let chunk_len = 4 + 2 * (ACCOUNT_LEN + 4 + SLOT_LEN) + 32;
+        buf.truncate(buf.len() - chunk_len);
+        let counts = buf.len() - chunk_len - 16;
+        buf[counts..counts + 8].copy_from_slice(&2u64.to_be_bytes());
+        buf[counts + 8..counts + 16].copy_from_slice(&1u64.to_be_bytes());
+
+        assert!(matches!(
+            read_snapshot(&mut buf.as_slice()),
+            Err(SnapshotError::StateRootMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_export_requires_matching_state_root() {
+        let db = InMemoryDB::new();
+        let header = Header {
+            number: 1,
+            state_root: B256::random(),
+            ..Default::default()
+        };
+        let block = ExecutedBlock::new(header, BlockBody::default(), Vec::new(), HashMap::new());
+        db.commit_block(block).unwrap();
+
+        let mut buf = Vec::new();
+        assert!(matches!(
+            SnapshotExporter::new(db.clone()).export(1, &mut buf),
+            Err(SnapshotError::StateRootMismatch { .. })
+        ));
+        assert!(matches!(
+            SnapshotExporter::new(db).export(2, &mut buf),
+            Err(SnapshotError::HeaderNotFound(2))
+        ));
+    }
+}
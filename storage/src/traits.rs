@@ -3,13 +3,18 @@ use std::collections::HashMap;
 use primitives::{
     account::Account,
     block::{
-        header::{Header, SealedHeader},
-        traits::BlockHeader,
+        body::SealedBlock,
+        header::SealedHeader,
+        traits::{Block, BlockHeader},
     },
-    types::{Address, B256, BlockHash, U256},
+    receipt::Receipt,
+    types::{Address, B256, BlockHash, StorageKey, StorageValue, TxHash, U256},
 };
 
-use crate::{db::Database, error::ProviderError};
+use crate::{
+    db::{AccountsPage, TransactionMeta},
+    error::ProviderError,
+};
 
 /// State which is created by StateProviderFactory
 pub trait StateProvider: AccountReader {
@@ -23,46 +28,58 @@ pub trait StateProvider: AccountReader {
             .map_or_else(|| Ok(None), |acc| Ok(Some(acc.nonce)))
     }
 
-    fn prepare_execute(&mut self) -> ProviderResult<HashMap<Address, Account>>;
-}
-
-impl Database for StateProviderBox {
-    fn basic(&self, address: &Address) -> Result<Option<Account>, crate::error::DatabaseError> {
-        todo!()
-    }
-
-    fn block_hash(&self, number: u64) -> Result<Option<BlockHash>, crate::error::DatabaseError> {
-        todo!()
-    }
+    // value of a storage slot of the account. None if the slot is empty.
+    fn account_storage(
+        &self,
+        addr: &Address,
+        key: &StorageKey,
+    ) -> ProviderResult<Option<StorageValue>>;
 
-    fn block_number(&self) -> u64 {
-        todo!()
-    }
+    fn prepare_execute(&mut self) -> ProviderResult<HashMap<Address, Account>>;
 
-    fn copy_state_from_block_no(
-        &self,
-        number: u64,
-    ) -> Result<std::collections::HashMap<Address, Account>, crate::error::DatabaseError> {
-        todo!()
-    }
+    // state root after the accounts of `diff` are applied to this state
+    fn state_root(&self, diff: &HashMap<Address, Account>) -> ProviderResult<B256>;
 }
 
 pub trait AccountReader {
     fn basic_account(&self, address: &Address) -> Result<Option<Account>, ProviderError>;
+    // accounts ordered by address, starting at `start`
+    fn accounts_page(&self, start: &Address, limit: usize) -> ProviderResult<AccountsPage>;
+    fn total_accounts(&self) -> ProviderResult<u64>;
 }
 
 pub type StateProviderBox = Box<dyn StateProvider>;
 
 impl<T: StateProvider + ?Sized> StateProvider for Box<T> {
+    fn account_storage(
+        &self,
+        addr: &Address,
+        key: &StorageKey,
+    ) -> ProviderResult<Option<StorageValue>> {
+        (**self).account_storage(addr, key)
+    }
+
     fn prepare_execute(&mut self) -> ProviderResult<HashMap<Address, Account>> {
         (**self).prepare_execute()
     }
+
+    fn state_root(&self, diff: &HashMap<Address, Account>) -> ProviderResult<B256> {
+        (**self).state_root(diff)
+    }
 }
 
 impl<T: StateProvider + ?Sized> AccountReader for Box<T> {
     fn basic_account(&self, address: &Address) -> Result<Option<Account>, ProviderError> {
         (**self).basic_account(address)
     }
+
+    fn accounts_page(&self, start: &Address, limit: usize) -> ProviderResult<AccountsPage> {
+        (**self).accounts_page(start, limit)
+    }
+
+    fn total_accounts(&self) -> ProviderResult<u64> {
+        (**self).total_accounts()
+    }
 }
 
 pub type ProviderResult<Ok> = Result<Ok, ProviderError>;
@@ -74,8 +91,32 @@ pub trait StateProviderFactory: Send + Sync {
     fn state_by_block_hash(&self, hash: BlockHash) -> ProviderResult<StateProviderBox>;
 }
 
-pub trait BlockReader: HeaderProvider {}
+/// Reads canonical blocks
+pub trait BlockReader: HeaderProvider {
+    type Block: Block<Header = Self::Header>;
+
+    fn body_by_number(&self, number: u64) -> ProviderResult<Option<<Self::Block as Block>::Body>>;
+    fn block_by_number(&self, number: u64) -> ProviderResult<Option<SealedBlock<Self::Block>>>;
+    fn block_by_hash(&self, hash: BlockHash) -> ProviderResult<Option<SealedBlock<Self::Block>>>;
+}
+
+/// Reads canonical transactions by their hash
+pub trait TransactionsProvider {
+    type Transaction;
+
+    fn transaction_by_hash(&self, hash: TxHash) -> ProviderResult<Option<Self::Transaction>>;
+    // where the transaction was included
+    fn transaction_block(&self, hash: TxHash) -> ProviderResult<Option<TransactionMeta>>;
+    fn receipt_by_hash(&self, hash: TxHash) -> ProviderResult<Option<Receipt>>;
+}
 
+/// Reads receipts of canonical blocks
+pub trait ReceiptProvider {
+    fn receipts_by_block(&self, number: u64) -> ProviderResult<Option<Vec<Receipt>>>;
+    fn receipt(&self, tx_hash: TxHash) -> ProviderResult<Option<Receipt>>;
+}
+
+/// Reads canonical headers
 pub trait HeaderProvider {
     type Header: BlockHeader;
 
@@ -84,4 +125,10 @@ pub trait HeaderProvider {
         &self,
         bloch_hash: BlockHash,
     ) -> ProviderResult<Option<SealedHeader<Self::Header>>>;
+    fn sealed_header(&self, number: u64) -> ProviderResult<Option<SealedHeader<Self::Header>>>;
+    fn header_by_number(&self, number: u64) -> ProviderResult<Option<Self::Header>> {
+        Ok(self.sealed_header(number)?.map(SealedHeader::into_header))
+    }
+    // block number of the canonical block hash
+    fn block_number(&self, hash: BlockHash) -> ProviderResult<Option<u64>>;
 }
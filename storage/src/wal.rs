@@ -0,0 +1,1161 @@
+//! Write-ahead log for the [InMemoryDB].
+//! Every change is appended to the log and synced before it is applied,
+//! so the database is rebuilt from the log on open and a crash never leaves a half-applied block.
+//!
+//! Layout:
+//! `magic | schema version | record..` and every record is
+//! `payload length | checksum of the length | payload | sha256 of the payload`,
+//! where the payload is `kind | data` and the checksum is the first 4 bytes of the sha256 of
+//! the length.
+//! A torn record at the end of the log is discarded on open, a corrupted record before it
+//! refuses to open the log. As the length is checked, a damaged length is never mistaken for a
+//! record that runs past the end of the log.
+//! Pruning is logged too, so pruned history stays pruned after a restart.
+use std::{
+    collections::HashMap,
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::Path,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use primitives::{
+    account::{Account, AccountStorage},
+    block::{
+        body::BlockBody,
+        header::{Header, SealedHeader},
+    },
+    receipt::Receipt,
+    types::{Address, B256, BlockHash, StorageKey, StorageValue, TxHash, U256},
+};
+use sha2::{Digest, Sha256};
+use transaction::{
+    TransactionSigned,
+    traits::{Decodable, Encodable},
+};
+
+use crate::{
+    db::{
+        AccountChangeset, AccountsPage, Database, DatabaseMut, ExecutedBlock, InMemoryDB,
+        StorageChangeset, TransactionMeta,
+    },
+    error::DatabaseError,
+    migration::{self, HEADER_LEN, SCHEMA_VERSION},
+};
+
+pub(crate) const COMMIT: u8 = 0;
+const UNWIND: u8 = 1;
+pub(crate) const INIT: u8 = 2;
+const REBUILD: u8 = 3;
+const PRUNE_STATE: u8 = 4;
+const PRUNE_RECEIPTS: u8 = 5;
+
+/// File the log is written to
+pub trait WalFile: Send {
+    /// Reads the whole log
+    fn read_all(&mut self) -> io::Result<Vec<u8>>;
+    /// Appends to the end of the log
+    fn append(&mut self, buf: &[u8]) -> io::Result<()>;
+    /// Makes every appended byte durable
+    fn sync(&mut self) -> io::Result<()>;
+    /// Cuts the log after `len` bytes
+    fn truncate(&mut self, len: u64) -> io::Result<()>;
+}
+
+impl WalFile for File {
+    fn read_all(&mut self) -> io::Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        self.seek(SeekFrom::Start(0))?;
+        self.read_to_end(&mut buf)?;
+        Ok(buf)
+    }
+
+    fn append(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.seek(SeekFrom::End(0))?;
+        self.write_all(buf)
+    }
+
+    fn sync(&mut self) -> io::Result<()> {
+        self.sync_data()
+    }
+
+    fn truncate(&mut self, len: u64) -> io::Result<()> {
+        self.set_len(len)?;
+        self.sync_all()
+    }
+}
+
+/// Change of the database that is logged before it is applied
+pub(crate) enum Record {
+    Commit(ExecutedBlock),
+    Unwind(u64),
+    Init(
+        SealedHeader,
+        HashMap<Address, Account>,
+        HashMap<Address, AccountStorage>,
+    ),
+    RebuildIndices,
+    PruneStateHistory(u64),
+    PruneReceipts(u64),
+}
+
+impl Record {
+    fn apply(self, db: &InMemoryDB) -> Result<(), DatabaseError> {
+        match self {
+            Record::Commit(block) => db.commit_block(block),
+            Record::Unwind(number) => db.unwind_to(number).map(|_| ()),
+            Record::Init(header, state, storage) => db.init_from_state(header, state, storage),
+            Record::RebuildIndices => db.rebuild_indices().map(|_| ()),
+            Record::PruneStateHistory(number) => db.prune_state_history(number).map(|_| ()),
+            Record::PruneReceipts(number) => db.prune_receipts(number).map(|_| ()),
+        }
+    }
+}
+
+struct Wal<F> {
+    file: F,
+    // length of every complete record
+    len: u64,
+    // a record that failed could not be cut off again, so nothing may be appended after it
+    poisoned: bool,
+}
+
+impl<F: WalFile> Wal<F> {
+    // Appends a record and returns where it starts.
+    fn append(&mut self, payload: &[u8]) -> Result<u64, DatabaseError> {
+        if self.poisoned {
+            return Err(DatabaseError::LogPoisoned);
+        }
+        let start = self.len;
+        let record = frame(payload);
+
+        if let Err(e) = self.file.append(&record).and_then(|_| self.file.sync()) {
+            // The failed record stays the last one, so it is discarded on open.
+            if self.file.truncate(start).is_err() {
+                self.poisoned = true;
+            }
+            return Err(e.into());
+        }
+        self.len += record.len() as u64;
+        Ok(start)
+    }
+
+    fn truncate(&mut self, len: u64) -> Result<(), DatabaseError> {
+        if self.poisoned {
+            return Err(DatabaseError::LogPoisoned);
+        }
+        if let Err(e) = self.file.truncate(len) {
+            self.poisoned = true;
+            return Err(e.into());
+        }
+        self.len = len;
+        Ok(())
+    }
+}
+
+/// [InMemoryDB] that survives restarts through a write-ahead log
+pub struct WalDB<F = File> {
+    db: InMemoryDB,
+    wal: Arc<Mutex<Wal<F>>>,
+}
+
+impl<F> Clone for WalDB<F> {
+    fn clone(&self) -> Self {
+        Self {
+            db: self.db.clone(),
+            wal: self.wal.clone(),
+        }
+    }
+}
+
+/// Log that was read on open and refuses every write, see [WalDB::open_read_only]
+pub struct ReadOnlyFile {
+    len: u64,
+}
+
+impl WalFile for ReadOnlyFile {
+    fn read_all(&mut self) -> io::Result<Vec<u8>> {
+        Err(read_only())
+    }
+
+    fn append(&mut self, _buf: &[u8]) -> io::Result<()> {
+        Err(read_only())
+    }
+
+    fn sync(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    // Nothing was ever appended, so cutting at the end changes nothing.
+    fn truncate(&mut self, len: u64) -> io::Result<()> {
+        if len != self.len {
+            return Err(read_only());
+        }
+        Ok(())
+    }
+}
+
+fn read_only() -> io::Error {
+    io::Error::new(io::ErrorKind::PermissionDenied, "log is opened read-only")
+}
+
+impl WalDB<File> {
+    /// Opens the log at the given path, creating it if it does not exist.
+    /// A log of an older schema version is migrated first, see [migration].
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, DatabaseError> {
+        let path = path.as_ref();
+        let log = match fs::read(path) {
+            Ok(log) => log,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e.into()),
+        };
+        if let Some(migrated) = migration::migrate(&log)? {
+            // The old log stays in place until the migrated one is complete.
+            let mut tmp = path.as_os_str().to_owned();
+            tmp.push(".migrate");
+            let mut file = File::create(&tmp)?;
+            file.write_all(&migrated)?;
+            file.sync_all()?;
+            fs::rename(&tmp, path)?;
+            sync_parent_dir(path)?;
+        }
+
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+        Self::open_with(file)
+    }
+}
+
+impl WalDB<ReadOnlyFile> {
+    /// Opens the log at the given path without writing to it, for inspecting a database.
+    /// The log must exist. A log of an older schema version is migrated in memory only and a
+    /// torn last record is skipped but left in the file. Every write fails.
+    pub fn open_read_only(path: impl AsRef<Path>) -> Result<Self, DatabaseError> {
+        let log = fs::read(path)?;
+        let log = migration::migrate(&log)?.unwrap_or(log);
+        let (db, len) = replay(&log)?;
+        Ok(Self::new(db, ReadOnlyFile { len }, len))
+    }
+}
+
+impl<F: WalFile> WalDB<F> {
+    /// Rebuilds the database by replaying the log.
+    /// A torn or inapplicable last record is cut off, a corrupted or inapplicable record before
+    /// it fails with [DatabaseError::CorruptLog].
+    /// The log must be new or have the current [SCHEMA_VERSION].
+    pub fn open_with(mut file: F) -> Result<Self, DatabaseError> {
+        let mut bytes = file.read_all()?;
+        match migration::schema_version(&bytes)? {
+            None => {
+                bytes = migration::header().to_vec();
+                file.truncate(0)?;
+                file.append(&bytes)?;
+                file.sync()?;
+            }
+            Some(SCHEMA_VERSION) => {}
+            Some(version) if version > SCHEMA_VERSION => {
+                return Err(DatabaseError::SchemaVersionTooNew(version));
+            }
+            Some(version) => return Err(DatabaseError::SchemaMigrationRequired(version)),
+        }
+        let (db, len) = replay(&bytes)?;
+        if len < bytes.len() as u64 {
+            file.truncate(len)?;
+        }
+        Ok(Self::new(db, file, len))
+    }
+
+    fn new(db: InMemoryDB, file: F, len: u64) -> Self {
+        Self {
+            db,
+            wal: Arc::new(Mutex::new(Wal {
+                file,
+                len,
+                poisoned: false,
+            })),
+        }
+    }
+
+    /// Database the log is applied to
+    pub fn db(&self) -> &InMemoryDB {
+        &self.db
+    }
+
+    fn lock_wal(&self) -> Result<MutexGuard<'_, Wal<F>>, DatabaseError> {
+        self.wal.lock().map_err(|_| DatabaseError::LockError)
+    }
+
+    // Logs the record and applies it. The record is removed again if it cannot be applied,
+    // and the log refuses further writes if removing it fails.
+    fn log_and_apply<T>(
+        &self,
+        payload: Vec<u8>,
+        apply: impl FnOnce(&InMemoryDB) -> Result<T, DatabaseError>,
+    ) -> Result<T, DatabaseError> {
+        let mut wal = self.lock_wal()?;
+        let start = wal.append(&payload)?;
+        match apply(&self.db) {
+            Ok(output) => Ok(output),
+            Err(e) => {
+                wal.truncate(start)?;
+                Err(e)
+            }
+        }
+    }
+}
+
+impl<F: WalFile> Database for WalDB<F> {
+    fn basic(&self, address: &Address) -> Result<Option<Account>, DatabaseError> {
+        self.db.basic(address)
+    }
+
+    fn basic_at_block(
+        &self,
+        address: &Address,
+        number: u64,
+    ) -> Result<Option<Account>, DatabaseError> {
+        self.db.basic_at_block(address, number)
+    }
+
+    fn block_hash(&self, number: u64) -> Result<Option<BlockHash>, DatabaseError> {
+        self.db.block_hash(number)
+    }
+
+    fn block_number(&self) -> u64 {
+        self.db.block_number()
+    }
+
+    fn copy_state_from_block_no(
+        &self,
+        number: u64,
+    ) -> Result<HashMap<Address, Account>, DatabaseError> {
+        self.db.copy_state_from_block_no(number)
+    }
+
+    fn header(&self, number: u64) -> Result<Option<Header>, DatabaseError> {
+        self.db.header(number)
+    }
+
+    fn body(&self, number: u64) -> Result<Option<BlockBody<TransactionSigned>>, DatabaseError> {
+        self.db.body(number)
+    }
+
+    fn block_number_by_hash(&self, hash: &BlockHash) -> Result<Option<u64>, DatabaseError> {
+        self.db.block_number_by_hash(hash)
+    }
+
+    fn transaction_meta(&self, hash: &TxHash) -> Result<Option<TransactionMeta>, DatabaseError> {
+        self.db.transaction_meta(hash)
+    }
+
+    fn receipts(&self, number: u64) -> Result<Option<Vec<Receipt>>, DatabaseError> {
+        self.db.receipts(number)
+    }
+
+    fn earliest_state_block(&self) -> Result<u64, DatabaseError> {
+        self.db.earliest_state_block()
+    }
+
+    fn accounts_range(&self, start: &Address, limit: usize) -> Result<AccountsPage, DatabaseError> {
+        self.db.accounts_range(start, limit)
+    }
+
+    fn account_count(&self) -> Result<u64, DatabaseError> {
+        self.db.account_count()
+    }
+
+    fn accounts_range_at_block(
+        &self,
+        number: u64,
+        start: &Address,
+        limit: usize,
+    ) -> Result<AccountsPage, DatabaseError> {
+        self.db.accounts_range_at_block(number, start, limit)
+    }
+
+    fn account_count_at_block(&self, number: u64) -> Result<u64, DatabaseError> {
+        self.db.account_count_at_block(number)
+    }
+
+    fn storage(
+        &self,
+        address: &Address,
+        key: &StorageKey,
+    ) -> Result<Option<StorageValue>, DatabaseError> {
+        self.db.storage(address, key)
+    }
+
+    fn storage_at_block(
+        &self,
+        address: &Address,
+        key: &StorageKey,
+        number: u64,
+    ) -> Result<Option<StorageValue>, DatabaseError> {
+        self.db.storage_at_block(address, key, number)
+    }
+
+    fn copy_storage_from_block_no(
+        &self,
+        number: u64,
+    ) -> Result<HashMap<Address, AccountStorage>, DatabaseError> {
+        self.db.copy_storage_from_block_no(number)
+    }
+
+    fn account_changeset(&self, number: u64) -> Result<Option<AccountChangeset>, DatabaseError> {
+        self.db.account_changeset(number)
+    }
+
+    fn storage_changeset(&self, number: u64) -> Result<Option<StorageChangeset>, DatabaseError> {
+        self.db.storage_changeset(number)
+    }
+}
+
+impl<F: WalFile> DatabaseMut for WalDB<F> {
+    fn commit_block(&self, block: ExecutedBlock) -> Result<(), DatabaseError> {
+        let payload = encode_commit(&block)?;
+        self.log_and_apply(payload, |db| db.commit_block(block))
+    }
+
+    fn unwind_to(&self, number: u64) -> Result<Vec<TransactionSigned>, DatabaseError> {
+        let mut payload = vec![UNWIND];
+        payload.extend_from_slice(&number.to_be_bytes());
+        self.log_and_apply(payload, |db| db.unwind_to(number))
+    }
+
+    // A run that prunes nothing is not logged, so an idle pruner does not grow the log.
+    fn prune_state_history(&self, number: u64) -> Result<u64, DatabaseError> {
+        if number.min(self.db.block_number()) <= self.db.earliest_state_block()? {
+            return Ok(0);
+        }
+        let mut payload = vec![PRUNE_STATE];
+        payload.extend_from_slice(&number.to_be_bytes());
+        self.log_and_apply(payload, |db| db.prune_state_history(number))
+    }
+
+    fn prune_receipts(&self, number: u64) -> Result<u64, DatabaseError> {
+        if number.min(self.db.block_number()) <= self.db.receipts_pruned_below()? {
+            return Ok(0);
+        }
+        let mut payload = vec![PRUNE_RECEIPTS];
+        payload.extend_from_slice(&number.to_be_bytes());
+        self.log_and_apply(payload, |db| db.prune_receipts(number))
+    }
+
+    fn init_from_state(
+        &self,
+        header: SealedHeader,
+        state: HashMap<Address, Account>,
+        storage: HashMap<Address, AccountStorage>,
+    ) -> Result<(), DatabaseError> {
+        let mut payload = vec![INIT];
+        put_header(&mut payload, header.header());
+        payload.extend_from_slice(header.hash().as_slice());
+        put_accounts(&mut payload, &state);
+        put_storage(&mut payload, &storage);
+        self.log_and_apply(payload, |db| db.init_from_state(header, state, storage))
+    }
+
+    // Logged like every other write, so a repair is part of the history of the log.
+    fn rebuild_indices(&self) -> Result<u64, DatabaseError> {
+        self.log_and_apply(vec![REBUILD], |db| db.rebuild_indices())
+    }
+}
+
+// Applies the records of a log with the current [SCHEMA_VERSION] to a new database and returns
+// it with the length of the log up to the last applied record.
+// A torn or inapplicable last record is left over from a crash or a failed write and is skipped.
+// A corrupted or inapplicable record before it fails with [DatabaseError::CorruptLog], because
+// the records after it cannot be replayed without it.
+fn replay(bytes: &[u8]) -> Result<(InMemoryDB, u64), DatabaseError> {
+    let db = InMemoryDB::new();
+    let mut offset = HEADER_LEN;
+    loop {
+        let (payload, next) = match next_record(bytes, offset) {
+            NextRecord::Complete(payload, next) => (payload, next),
+            NextRecord::Torn => break,
+            NextRecord::Corrupt => {
+                return Err(DatabaseError::CorruptLog {
+                    offset: offset as u64,
+                });
+            }
+        };
+        let record = decode_record(payload).ok_or(DatabaseError::CorruptLog {
+            offset: offset as u64,
+        })?;
+        // Applying is deterministic, so a record that fails now also failed when it was
+        // written. Only the last record can be such a leftover, see [Wal::truncate].
+        if record.apply(&db).is_err() {
+            if next < bytes.len() {
+                return Err(DatabaseError::CorruptLog {
+                    offset: offset as u64,
+                });
+            }
+            break;
+        }
+        offset = next;
+    }
+    Ok((db, offset as u64))
+}
+
+// Makes a rename in the directory of the path durable
+fn sync_parent_dir(path: &Path) -> io::Result<()> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    File::open(dir)?.sync_all()
+}
+
+// length + checksum of the length
+const RECORD_HEADER_LEN: usize = 4 + 4;
+
+// Record as it is written to the log
+pub(crate) fn frame(payload: &[u8]) -> Vec<u8> {
+    let len = (payload.len() as u32).to_be_bytes();
+    let mut record = Vec::with_capacity(RECORD_HEADER_LEN + payload.len() + 32);
+    record.extend_from_slice(&len);
+    record.extend_from_slice(&length_checksum(&len));
+    record.extend_from_slice(payload);
+    record.extend_from_slice(&Sha256::digest(payload));
+    record
+}
+
+fn length_checksum(len: &[u8]) -> [u8; 4] {
+    Sha256::digest(len)[..4].try_into().expect("4 bytes")
+}
+
+pub(crate) enum NextRecord<'a> {
+    // payload and the offset after the record
+    Complete(&'a [u8], usize),
+    // the log ends inside the record, or the record ends the log and its checksum is wrong,
+    // which is what a crash during the last append leaves behind
+    Torn,
+    // the length or the payload is damaged and the record is not the last one,
+    // or where it ends cannot be told
+    Corrupt,
+}
+
+pub(crate) fn next_record(bytes: &[u8], offset: usize) -> NextRecord<'_> {
+    let Some(header) = bytes.get(offset..offset + RECORD_HEADER_LEN) else {
+        return NextRecord::Torn;
+    };
+    let (len, checksum) = header.split_at(4);
+    if length_checksum(len) != checksum {
+        return NextRecord::Corrupt;
+    }
+    // The length is intact, so a record running past the end was cut off by a crash.
+    let start = offset + RECORD_HEADER_LEN;
+    let end = start + u32::from_be_bytes(len.try_into().unwrap()) as usize;
+    if end + 32 > bytes.len() {
+        return NextRecord::Torn;
+    }
+    let payload = &bytes[start..end];
+    let end = end + 32;
+    if Sha256::digest(payload).as_slice() == &bytes[end - 32..end] {
+        NextRecord::Complete(payload, end)
+    } else if end == bytes.len() {
+        NextRecord::Torn
+    } else {
+        NextRecord::Corrupt
+    }
+}
+
+fn encode_commit(block: &ExecutedBlock) -> Result<Vec<u8>, DatabaseError> {
+    let mut payload = vec![COMMIT];
+    put_header(&mut payload, &block.header);
+
+    payload.extend_from_slice(&(block.body.transaction.len() as u32).to_be_bytes());
+    for tx in &block.body.transaction {
+        let raw = tx
+            .encode()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{:?}", e)))?;
+        // The envelope encoding leaves out the type byte that decoding expects.
+        payload.extend_from_slice(&(raw.len() as u32 + 1).to_be_bytes());
+        payload.push(tx.tx_type());
+        payload.extend_from_slice(&raw);
+    }
+
+    payload.extend_from_slice(&(block.receipts.len() as u32).to_be_bytes());
+    for receipt in &block.receipts {
+        payload.push(receipt.tx_type);
+        payload.extend_from_slice(receipt.tx_hash.as_slice());
+        payload.push(receipt.success as u8);
+        payload.extend_from_slice(&receipt.fee.to_be_bytes::<32>());
+        payload.extend_from_slice(&receipt.cumulative_fee.to_be_bytes::<32>());
+        payload.extend_from_slice(&receipt.sender_nonce.to_be_bytes());
+        payload.extend_from_slice(&receipt.receiver_nonce.to_be_bytes());
+    }
+
+    put_accounts(&mut payload, &block.state_diff);
+    put_storage(&mut payload, &block.storage_diff);
+    Ok(payload)
+}
+
+fn put_header(buf: &mut Vec<u8>, header: &Header) {
+    buf.extend_from_slice(header.previous_hash.as_slice());
+    buf.extend_from_slice(&header.number.to_be_bytes());
+    buf.extend_from_slice(header.transaction_root.as_slice());
+    buf.extend_from_slice(header.state_root.as_slice());
+    buf.extend_from_slice(header.receipts_root.as_slice());
+    buf.extend_from_slice(&header.timestamp.to_be_bytes());
+    buf.extend_from_slice(header.beneficiary.get_addr());
+}
+
+fn put_accounts(buf: &mut Vec<u8>, accounts: &HashMap<Address, Account>) {
+    buf.extend_from_slice(&(accounts.len() as u32).to_be_bytes());
+    for (address, account) in accounts {
+        buf.extend_from_slice(address.get_addr());
+        buf.extend_from_slice(&account.nonce.to_be_bytes());
+        buf.extend_from_slice(&account.balance.to_be_bytes::<32>());
+    }
+}
+
+fn put_storage(buf: &mut Vec<u8>, storage: &HashMap<Address, AccountStorage>) {
+    buf.extend_from_slice(&(storage.len() as u32).to_be_bytes());
+    for (address, slots) in storage {
+        buf.extend_from_slice(address.get_addr());
+        buf.extend_from_slice(&(slots.len() as u32).to_be_bytes());
+        for (key, value) in slots {
+            buf.extend_from_slice(key.as_slice());
+            buf.extend_from_slice(&value.to_be_bytes::<32>());
+        }
+    }
+}
+
+// None if the payload is malformed
+pub(crate) fn decode_record(payload: &[u8]) -> Option<Record> {
+    let mut reader = Reader(payload);
+    let record = match reader.u8()? {
+        COMMIT => {
+            let header = reader.header()?;
+            let mut transaction = Vec::new();
+            for _ in 0..reader.u32()? {
+                let len = reader.u32()? as usize;
+                let raw = reader.take(len)?.to_vec();
+                transaction.push(TransactionSigned::decode(&raw).ok()?.0);
+            }
+            let mut receipts = Vec::new();
+            for _ in 0..reader.u32()? {
+                receipts.push(Receipt {
+                    tx_type: reader.u8()?,
+                    tx_hash: reader.b256()?,
+                    success: reader.u8()? != 0,
+                    fee: reader.u256()?,
+                    cumulative_fee: reader.u256()?,
+                    sender_nonce: reader.u64()?,
+                    receiver_nonce: reader.u64()?,
+                });
+            }
+            let state_diff = reader.accounts()?;
+            let storage_diff = reader.storage()?;
+            let block = ExecutedBlock::new(header, BlockBody { transaction }, receipts, state_diff)
+                .with_storage_diff(storage_diff);
+            Record::Commit(block)
+        }
+        UNWIND => Record::Unwind(reader.u64()?),
+        INIT => {
+            let header = reader.header()?;
+            let hash = reader.b256()?;
+            Record::Init(
+                SealedHeader::new(header, hash),
+                reader.accounts()?,
+                reader.storage()?,
+            )
+        }
+        REBUILD => Record::RebuildIndices,
+        PRUNE_STATE => Record::PruneStateHistory(reader.u64()?),
+        PRUNE_RECEIPTS => Record::PruneReceipts(reader.u64()?),
+        _ => return None,
+    };
+    reader.0.is_empty().then_some(record)
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.0.len() < len {
+            return None;
+        }
+        let (head, tail) = self.0.split_at(len);
+        self.0 = tail;
+        Some(head)
+    }
+
+    fn array<const N: usize>(&mut self) -> Option<[u8; N]> {
+        self.take(N)?.try_into().ok()
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        Some(self.array::<1>()?[0])
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_be_bytes(self.array()?))
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        Some(u64::from_be_bytes(self.array()?))
+    }
+
+    fn b256(&mut self) -> Option<B256> {
+        Some(B256::from(self.array::<32>()?))
+    }
+
+    fn u256(&mut self) -> Option<U256> {
+        Some(U256::from_be_bytes(self.array::<32>()?))
+    }
+
+    fn address(&mut self) -> Option<Address> {
+        Some(Address::from_byte(self.array()?))
+    }
+
+    fn header(&mut self) -> Option<Header> {
+        Some(Header {
+            previous_hash: self.b256()?,
+            number: self.u64()?,
+            transaction_root: self.b256()?,
+            state_root: self.b256()?,
+            receipts_root: self.b256()?,
+            timestamp: self.u64()?,
+            beneficiary: self.address()?,
+        })
+    }
+
+    fn accounts(&mut self) -> Option<HashMap<Address, Account>> {
+        let mut accounts = HashMap::new();
+        for _ in 0..self.u32()? {
+            let address = self.address()?;
+            let account = Account {
+                nonce: self.u64()?,
+                balance: self.u256()?,
+            };
+            accounts.insert(address, account);
+        }
+        Some(accounts)
+    }
+
+    fn storage(&mut self) -> Option<HashMap<Address, AccountStorage>> {
+        let mut storage = HashMap::new();
+        for _ in 0..self.u32()? {
+            let address = self.address()?;
+            let mut slots = AccountStorage::new();
+            for _ in 0..self.u32()? {
+                slots.insert(self.b256()?, self.u256()?);
+            }
+            storage.insert(address, slots);
+        }
+        Some(storage)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::make_transactions;
+
+    /// In-memory log file that crashes after writing `budget` bytes.
+    /// After the crash every call fails, like a killed process, and only `data` survives.
+    #[derive(Clone, Default)]
+    struct FaultyFile {
+        data: Arc<Mutex<Vec<u8>>>,
+        budget: Option<usize>,
+        fail_sync: bool,
+        fail_truncate: bool,
+        crashed: bool,
+    }
+
+    impl FaultyFile {
+        fn reopen(&self) -> Self {
+            Self {
+                data: Arc::new(Mutex::new(self.data.lock().unwrap().clone())),
+                ..Default::default()
+            }
+        }
+
+        fn crash(&mut self) -> io::Error {
+            self.crashed = true;
+            io::Error::other("crashed")
+        }
+
+        fn check(&mut self) -> io::Result<()> {
+            if self.crashed {
+                return Err(io::Error::other("crashed"));
+            }
+            Ok(())
+        }
+    }
+
+    impl WalFile for FaultyFile {
+        fn read_all(&mut self) -> io::Result<Vec<u8>> {
+            self.check()?;
+            Ok(self.data.lock().unwrap().clone())
+        }
+
+        fn append(&mut self, buf: &[u8]) -> io::Result<()> {
+            self.check()?;
+            match self.budget {
+                Some(budget) if budget < buf.len() => {
+                    self.data.lock().unwrap().extend_from_slice(&buf[..budget]);
+                    Err(self.crash())
+                }
+                _ => {
+                    self.budget = self.budget.map(|budget| budget - buf.len());
+                    self.data.lock().unwrap().extend_from_slice(buf);
+                    Ok(())
+                }
+            }
+        }
+
+        fn sync(&mut self) -> io::Result<()> {
+            self.check()?;
+            if self.fail_sync {
+                return Err(self.crash());
+            }
+            Ok(())
+        }
+
+        fn truncate(&mut self, len: u64) -> io::Result<()> {
+            self.check()?;
+            if self.fail_truncate {
+                return Err(io::Error::other("truncate failed"));
+            }
+            self.data.lock().unwrap().truncate(len as usize);
+            Ok(())
+        }
+    }
+
+    fn next_block(db: &impl Database, number: u64, with_txs: bool) -> ExecutedBlock {
+        let header = Header {
+            previous_hash: db.block_hash(number - 1).unwrap().unwrap_or_default(),
+            number,
+            timestamp: number,
+            beneficiary: Address::from_byte([0xbe; 20]),
+            ..Default::default()
+        };
+        let (body, receipts) = if with_txs {
+            let transaction = make_transactions();
+            let receipts = transaction
+                .iter()
+                .map(|tx| Receipt {
+                    tx_hash: tx.hash(),
+                    success: true,
+                    fee: U256::from(number),
+                    ..Default::default()
+                })
+                .collect();
+            (BlockBody { transaction }, receipts)
+        } else {
+            (BlockBody::default(), Vec::new())
+        };
+        let address = Address::from_byte([number as u8; 20]);
+        let account = Account {
+            nonce: number,
+            balance: U256::from(number * 10),
+        };
+        let slots = AccountStorage::from([(StorageKey::with_last_byte(1), U256::from(number))]);
+        ExecutedBlock::new(
+            header,
+            body,
+            receipts,
+            HashMap::from([(address.clone(), account)]),
+        )
+        .with_storage_diff(HashMap::from([(address, slots)]))
+    }
+
+    fn assert_same_head(db: &impl Database, expected: &impl Database) {
+        let head = expected.block_number();
+        assert_eq!(db.block_number(), head);
+        assert_eq!(db.header(head).unwrap(), expected.header(head).unwrap());
+        assert_eq!(
+            db.block_hash(head).unwrap(),
+            expected.block_hash(head).unwrap()
+        );
+        assert_eq!(
+            db.copy_state_from_block_no(head).unwrap(),
+            expected.copy_state_from_block_no(head).unwrap()
+        );
+        assert_eq!(
+            db.copy_storage_from_block_no(head).unwrap(),
+            expected.copy_storage_from_block_no(head).unwrap()
+        );
+        assert_eq!(db.receipts(head).unwrap(), expected.receipts(head).unwrap());
+    }
+
+    #[test]
+    fn test_reopen_replays_log() {
+        let file = FaultyFile::default();
+        let db = WalDB::open_with(file.clone()).unwrap();
+        let expected = InMemoryDB::new();
+        for number in 1..=4 {
+            db.commit_block(next_block(&db, number, number % 2 == 0))
+                .unwrap();
+            expected
+                .commit_block(next_block(&expected, number, number % 2 == 0))
+                .unwrap();
+        }
+        db.unwind_to(3).unwrap();
+        expected.unwind_to(3).unwrap();
+
+        let reopened = WalDB::open_with(file.reopen()).unwrap();
+        assert_same_head(&reopened, &expected);
+        let tx = &make_transactions()[0];
+        assert_eq!(
+            reopened.transaction_meta(&tx.hash()).unwrap(),
+            expected.transaction_meta(&tx.hash()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_crash_at_every_byte_of_a_commit() {
+        let file = FaultyFile::default();
+        let db = WalDB::open_with(file.clone()).unwrap();
+        for number in 1..=2 {
+            db.commit_block(next_block(&db, number, true)).unwrap();
+        }
+        let before = file.data.lock().unwrap().len();
+        let record_len =
+            RECORD_HEADER_LEN + encode_commit(&next_block(&db, 3, true)).unwrap().len() + 32;
+
+        for budget in 0..=record_len {
+            let crashing = FaultyFile {
+                budget: Some(budget),
+                fail_sync: budget == record_len,
+                ..file.reopen()
+            };
+            let db = WalDB::open_with(crashing.clone()).unwrap();
+            assert!(db.commit_block(next_block(&db, 3, true)).is_err());
+            // the failed commit is never visible to the running database
+            assert_eq!(db.block_number(), 2);
+
+            let reopened = WalDB::open_with(crashing.reopen()).unwrap();
+            if budget < record_len {
+                // torn record is discarded
+                assert_eq!(reopened.block_number(), 2);
+                assert_eq!(
+                    crashing.reopen().data.lock().unwrap().len(),
+                    before + budget
+                );
+            } else {
+                // complete record that was never applied is replayed
+                let expected = WalDB::open_with(file.reopen()).unwrap();
+                expected
+                    .commit_block(next_block(&expected, 3, true))
+                    .unwrap();
+                assert_same_head(&reopened, &expected);
+            }
+            // the log keeps working after the discarded record
+            reopened
+                .commit_block(next_block(&reopened, reopened.block_number() + 1, false))
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn test_corrupted_last_record_is_discarded() {
+        let file = FaultyFile::default();
+        let db = WalDB::open_with(file.clone()).unwrap();
+        db.commit_block(next_block(&db, 1, false)).unwrap();
+        let first = db.db().clone();
+        db.commit_block(next_block(&db, 2, true)).unwrap();
+
+        let corrupted = file.reopen();
+        let at = corrupted.data.lock().unwrap().len() - 40;
+        corrupted.data.lock().unwrap()[at] ^= 1;
+
+        let reopened = WalDB::open_with(corrupted.clone()).unwrap();
+        assert_eq!(reopened.block_number(), 1);
+        // verify against a database that only saw the first block
+        first.unwind_to(1).unwrap();
+        assert_same_head(&reopened, &first);
+    }
+
+    #[test]
+    fn test_corruption_before_the_tail_is_refused() {
+        let file = FaultyFile::default();
+        let db = WalDB::open_with(file.clone()).unwrap();
+        db.commit_block(next_block(&db, 1, false)).unwrap();
+        db.commit_block(next_block(&db, 2, true)).unwrap();
+
+        // a damaged payload, and a damaged length that would run past the end of the log
+        for (at, flip) in [(HEADER_LEN + RECORD_HEADER_LEN + 10, 1), (HEADER_LEN, 0x80)] {
+            let corrupted = file.reopen();
+            corrupted.data.lock().unwrap()[at] ^= flip;
+            let len = corrupted.data.lock().unwrap().len();
+
+            assert!(matches!(
+                WalDB::open_with(corrupted.clone()),
+                Err(DatabaseError::CorruptLog { offset }) if offset == HEADER_LEN as u64
+            ));
+            // the intact records after the damage are kept
+            assert_eq!(corrupted.data.lock().unwrap().len(), len);
+        }
+    }
+
+    #[test]
+    fn test_inapplicable_record_before_the_tail_is_refused() {
+        let file = FaultyFile::default();
+        let db = WalDB::open_with(file.clone()).unwrap();
+        db.commit_block(next_block(&db, 1, false)).unwrap();
+        let gap = next_block(&db, 3, false);
+        let after = next_block(&db, 2, false);
+
+        let log = file.reopen();
+        let offset = log.data.lock().unwrap().len();
+        log.data
+            .lock()
+            .unwrap()
+            .extend(frame(&encode_commit(&gap).unwrap()));
+        assert_eq!(WalDB::open_with(log.reopen()).unwrap().block_number(), 1);
+
+        log.data
+            .lock()
+            .unwrap()
+            .extend(frame(&encode_commit(&after).unwrap()));
+        assert!(matches!(
+            WalDB::open_with(log),
+            Err(DatabaseError::CorruptLog { offset: at }) if at == offset as u64
+        ));
+    }
+
+    #[test]
+    fn test_failed_truncate_poisons_the_log() {
+        let file = FaultyFile::default();
+        let db = WalDB::open_with(file.clone()).unwrap();
+        db.commit_block(next_block(&db, 1, false)).unwrap();
+
+        let failing = FaultyFile {
+            fail_truncate: true,
+            ..file.reopen()
+        };
+        let db = WalDB::open_with(failing.clone()).unwrap();
+        assert!(matches!(
+            db.commit_block(next_block(&db, 3, false)),
+            Err(DatabaseError::Io(_))
+        ));
+        // nothing may follow the record that could not be removed
+        assert!(matches!(
+            db.commit_block(next_block(&db, 2, false)),
+            Err(DatabaseError::LogPoisoned)
+        ));
+        assert_eq!(db.block_number(), 1);
+
+        // the leftover record is the last one and is discarded on open
+        let reopened = WalDB::open_with(failing.reopen()).unwrap();
+        assert_eq!(reopened.block_number(), 1);
+        reopened
+            .commit_block(next_block(&reopened, 2, false))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_rejected_commit_is_not_logged() {
+        let file = FaultyFile::default();
+        let db = WalDB::open_with(file.clone()).unwrap();
+        db.commit_block(next_block(&db, 1, false)).unwrap();
+        let len = file.data.lock().unwrap().len();
+
+        let gap = next_block(&db, 3, false);
+        assert!(matches!(
+            db.commit_block(gap),
+            Err(DatabaseError::InvalidBlockNumber(3))
+        ));
+        assert_eq!(file.data.lock().unwrap().len(), len);
+    }
+
+    #[test]
+    fn test_rebuild_indices_is_logged() {
+        let file = FaultyFile::default();
+        let db = WalDB::open_with(file.clone()).unwrap();
+        db.commit_block(next_block(&db, 1, true)).unwrap();
+        let block = db.block_hash(1).unwrap().unwrap();
+        let tx = make_transactions()[0].hash();
+        db.db().remove_index_entries(&block, &tx);
+        let len = file.data.lock().unwrap().len();
+
+        assert_eq!(db.rebuild_indices().unwrap(), 2);
+        assert_eq!(
+            file.data.lock().unwrap().len(),
+            len + RECORD_HEADER_LEN + 1 + 32
+        );
+        let reopened = WalDB::open_with(file.reopen()).unwrap();
+        assert_eq!(reopened.block_number_by_hash(&block).unwrap(), Some(1));
+        assert_eq!(reopened.rebuild_indices().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_pruning_is_logged() {
+        let file = FaultyFile::default();
+        let db = WalDB::open_with(file.clone()).unwrap();
+        for number in 1..=4 {
+            db.commit_block(next_block(&db, number, true)).unwrap();
+        }
+        assert!(db.prune_state_history(3).unwrap() > 0);
+        assert_eq!(db.prune_receipts(3).unwrap(), 3);
+        let len = file.data.lock().unwrap().len();
+        assert_eq!(db.prune_state_history(3).unwrap(), 0);
+        assert_eq!(db.prune_receipts(2).unwrap(), 0);
+        assert_eq!(file.data.lock().unwrap().len(), len);
+
+        let reopened = WalDB::open_with(file.reopen()).unwrap();
+        assert_eq!(reopened.earliest_state_block().unwrap(), 3);
+        assert!(reopened.copy_state_from_block_no(2).is_err());
+        assert!(reopened.copy_state_from_block_no(3).is_ok());
+        assert_eq!(reopened.receipts(1).unwrap(), None);
+        assert!(reopened.receipts(3).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_open_read_only() {
+        let path = std::env::temp_dir().join(format!("pint-wal-{}", B256::random()));
+        assert!(matches!(
+            WalDB::open_read_only(&path),
+            Err(DatabaseError::Io(e)) if e.kind() == io::ErrorKind::NotFound
+        ));
+        assert!(!path.exists());
+
+        let db = WalDB::open(&path).unwrap();
+        db.commit_block(next_block(&db, 1, true)).unwrap();
+        drop(db);
+        // a torn record is skipped but stays in the file
+        let mut log = fs::read(&path).unwrap();
+        log.extend_from_slice(&[0, 0, 1]);
+        fs::write(&path, &log).unwrap();
+
+        let read_only = WalDB::open_read_only(&path).unwrap();
+        assert_eq!(read_only.block_number(), 1);
+        assert!(matches!(
+            read_only.commit_block(next_block(&read_only, 2, false)),
+            Err(DatabaseError::Io(e)) if e.kind() == io::ErrorKind::PermissionDenied
+        ));
+        assert!(read_only.rebuild_indices().is_err());
+        assert_eq!(read_only.block_number(), 1);
+        assert_eq!(fs::read(&path).unwrap(), log);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_open_file() {
+        let path = std::env::temp_dir().join(format!("pint-wal-{}", B256::random()));
+        let db = WalDB::open(&path).unwrap();
+        let header = SealedHeader::seal_slow(Header {
+            number: 5,
+            ..Default::default()
+        });
+        let address = Address::random();
+        let state = HashMap::from([(address.clone(), Account::default())]);
+        db.init_from_state(header, state, HashMap::new()).unwrap();
+        db.commit_block(next_block(&db, 6, true)).unwrap();
+        drop(db);
+
+        let reopened = WalDB::open(&path).unwrap();
+        assert_eq!(reopened.block_number(), 6);
+        assert_eq!(reopened.earliest_state_block().unwrap(), 5);
+        assert_eq!(reopened.basic(&address).unwrap(), Some(Account::default()));
+        std::fs::remove_file(path).unwrap();
+    }
+}
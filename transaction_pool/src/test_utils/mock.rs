@@ -11,12 +11,17 @@ use parking_lot::Mutex;
 use paste::paste;
 use primitives::{
     account::Account,
+    proofs::calculate_state_root,
+    signature::Signature,
     types::{Address, B256, BlockHash, ChainId, StorageKey, StorageValue, TxHash, U256},
 };
-use storage::traits::{
-    AccountReader, ProviderResult, StateProvider, StateProviderBox, StateProviderFactory,
+use storage::{
+    db::AccountsPage,
+    traits::{
+        AccountReader, ProviderResult, StateProvider, StateProviderBox, StateProviderFactory,
+    },
 };
-use transaction::transaction::TxEnvelope;
+use transaction::{PintTx, signed::Signed, transaction::TxEnvelope};
 
 /// Mocking Types
 pub type MockValidTx = ValidPoolTransaction<MockTransaction>;
@@ -181,6 +186,20 @@ impl PoolTransaction for MockTransaction {
     fn from_pooled(_: transaction::signed::Recovered<Self::Pooled>) -> Self {
         MockTransaction::pint_tx()
     }
+
+    // Mocked transactions are not signed, so the envelope carries an empty signature.
+    fn clone_into_consensus(&self) -> transaction::signed::Recovered<Self::Pooled> {
+        let tx = PintTx {
+            chain_id: self.get_chain_id(),
+            nonce: self.get_nonce(),
+            to: self.get_to(),
+            fee: self.get_fee(),
+            value: self.get_value(),
+        };
+        let signature = Signature::from_bytes_and_parity(&[0; 64], false);
+        let signed = Signed::new(tx, signature, self.get_hash());
+        transaction::signed::Recovered::new_unchecked(TxEnvelope::Pint(signed), self.get_sender())
+    }
 }
 
 /// A provider for mocking!
@@ -196,9 +215,46 @@ impl MockPintProvider {
 }
 
 impl StateProvider for MockPintProvider {
+    fn account_storage(
+        &self,
+        addr: &Address,
+        key: &StorageKey,
+    ) -> ProviderResult<Option<StorageValue>> {
+        Ok(self
+            .accounts
+            .lock()
+            .get(addr)
+            .and_then(|extend_account| extend_account.storage.get(key))
+            .copied())
+    }
+
     fn prepare_execute(&mut self) -> ProviderResult<HashMap<Address, Account>> {
         todo!()
     }
+
+    fn state_root(&self, diff: &HashMap<Address, Account>) -> ProviderResult<B256> {
+        let accounts = self.accounts.lock();
+        let mut state: HashMap<_, _> = accounts
+            .iter()
+            .map(|(address, extend_account)| (address.clone(), extend_account.account))
+            .collect();
+        state.extend(
+            diff.iter()
+                .map(|(address, account)| (address.clone(), *account)),
+        );
+        let storage = accounts
+            .iter()
+            .map(|(address, extend_account)| {
+                let slots = extend_account
+                    .storage
+                    .iter()
+                    .map(|(k, v)| (*k, *v))
+                    .collect();
+                (address.clone(), slots)
+            })
+            .collect();
+        Ok(calculate_state_root(&state, &storage))
+    }
 }
 impl AccountReader for MockPintProvider {
     fn basic_account(
@@ -210,6 +266,23 @@ impl AccountReader for MockPintProvider {
             None => Ok(None),
         }
     }
+
+    fn accounts_page(&self, start: &Address, limit: usize) -> ProviderResult<AccountsPage> {
+        let mut accounts: Vec<_> = self
+            .accounts
+            .lock()
+            .iter()
+            .filter(|(address, _)| *address >= start)
+            .map(|(address, extend_account)| (address.clone(), extend_account.account))
+            .collect();
+        accounts.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+        accounts.truncate(limit.saturating_add(1));
+        Ok(AccountsPage::from_sorted(accounts, limit))
+    }
+
+    fn total_accounts(&self) -> ProviderResult<u64> {
+        Ok(self.accounts.lock().len() as u64)
+    }
 }
 
 impl StateProviderFactory for MockPintProvider {
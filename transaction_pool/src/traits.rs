@@ -57,6 +57,8 @@ pub trait PoolTransaction: Debug + Transaction {
     fn sender(&self) -> Address;
     fn cost(&self) -> U256;
     fn from_pooled(tx: Recovered<Self::Pooled>) -> Self;
+    // signed transaction to include in a block
+    fn clone_into_consensus(&self) -> Recovered<Self::Pooled>;
 }
 
 /// The default [`PoolTransaction`] for the [Pool](crate::Pool)
@@ -112,6 +114,10 @@ impl PoolTransaction for PintPooledTransaction {
     fn from_pooled(tx: Recovered<Self::Pooled>) -> Self {
         Self { transaction: tx }
     }
+
+    fn clone_into_consensus(&self) -> Recovered<Self::Pooled> {
+        self.transaction.clone()
+    }
 }
 
 /// BestTransactions of the Pool's transaction